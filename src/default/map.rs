@@ -0,0 +1,131 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use crate::error::TullyError;
+use crate::evaluator::{Callable, Evaluator};
+use crate::value::{MapKey, Value};
+
+fn map_key(function: &str, key: &Value) -> Result<MapKey, TullyError> {
+    match key.to_key() {
+        Some(key) => Ok(key),
+        None => Err(TullyError::RunTimeMessage(format!(
+            "{} expects a string, number or boolean key, got {}",
+            function,
+            key.to_string()
+        ))),
+    }
+}
+
+fn not_a_map(function: &str, value: &Value) -> TullyError {
+    TullyError::RunTimeMessage(format!(
+        "{} expects a map, got {}",
+        function,
+        value.to_string()
+    ))
+}
+
+pub struct Keys;
+
+impl Callable for Keys {
+    fn arity(&self) -> usize {
+        return 1;
+    }
+
+    fn call(
+        &self,
+        _evaluator: &mut Evaluator,
+        arguments: Vec<Rc<Value>>,
+    ) -> Result<Rc<Value>, TullyError> {
+        match &*arguments[0] {
+            Value::Map(map) => Ok(Rc::new(Value::List(Rc::new(RefCell::new(
+                map.borrow().keys(),
+            ))))),
+            value => Err(not_a_map("keys", value)),
+        }
+    }
+
+    fn to_string(&self) -> String {
+        String::from("<native fn keys>")
+    }
+}
+
+pub struct Values;
+
+impl Callable for Values {
+    fn arity(&self) -> usize {
+        return 1;
+    }
+
+    fn call(
+        &self,
+        _evaluator: &mut Evaluator,
+        arguments: Vec<Rc<Value>>,
+    ) -> Result<Rc<Value>, TullyError> {
+        match &*arguments[0] {
+            Value::Map(map) => Ok(Rc::new(Value::List(Rc::new(RefCell::new(
+                map.borrow().values(),
+            ))))),
+            value => Err(not_a_map("values", value)),
+        }
+    }
+
+    fn to_string(&self) -> String {
+        String::from("<native fn values>")
+    }
+}
+
+pub struct Has;
+
+impl Callable for Has {
+    fn arity(&self) -> usize {
+        return 2;
+    }
+
+    fn call(
+        &self,
+        _evaluator: &mut Evaluator,
+        arguments: Vec<Rc<Value>>,
+    ) -> Result<Rc<Value>, TullyError> {
+        match &*arguments[0] {
+            Value::Map(map) => {
+                let key = map_key("has", &arguments[1])?;
+                Ok(Rc::new(Value::Boolean(map.borrow().contains(&key))))
+            }
+            value => Err(not_a_map("has", value)),
+        }
+    }
+
+    fn to_string(&self) -> String {
+        String::from("<native fn has>")
+    }
+}
+
+pub struct Remove;
+
+impl Callable for Remove {
+    fn arity(&self) -> usize {
+        return 2;
+    }
+
+    // returns the removed value, or nil when the key was not present
+    fn call(
+        &self,
+        evaluator: &mut Evaluator,
+        arguments: Vec<Rc<Value>>,
+    ) -> Result<Rc<Value>, TullyError> {
+        match &*arguments[0] {
+            Value::Map(map) => {
+                let key = map_key("remove", &arguments[1])?;
+                match map.borrow_mut().remove(&key) {
+                    Some(value) => Ok(value),
+                    None => Ok(Rc::clone(&evaluator.constants.nil)),
+                }
+            }
+            value => Err(not_a_map("remove", value)),
+        }
+    }
+
+    fn to_string(&self) -> String {
+        String::from("<native fn remove>")
+    }
+}
@@ -1,5 +1,11 @@
 pub use clock::Clock;
+pub use map::{Has, Keys, Remove, Values};
+#[cfg(feature = "bignum")]
+pub use number::{ToBigInt, ToDecimal};
+pub use number::{ToFloat, ToInt};
 pub use random::Random;
 pub use random::RandomAlphaNumeric;
 mod clock;
+mod map;
+mod number;
 mod random;
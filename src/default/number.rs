@@ -0,0 +1,168 @@
+use std::rc::Rc;
+
+#[cfg(feature = "bignum")]
+use bigdecimal::BigDecimal;
+#[cfg(feature = "bignum")]
+use num_bigint::BigInt;
+#[cfg(feature = "bignum")]
+use num_traits::{FromPrimitive, ToPrimitive};
+
+use crate::error::TullyError;
+use crate::evaluator::{Callable, Evaluator};
+use crate::value::{float_to_int, Value};
+
+fn not_a_number(function: &str, value: &Value) -> TullyError {
+    TullyError::RunTimeMessage(format!(
+        "{} cannot convert {} to a number",
+        function,
+        value.to_string()
+    ))
+}
+
+pub struct ToInt;
+
+impl Callable for ToInt {
+    fn arity(&self) -> usize {
+        return 1;
+    }
+
+    fn call(
+        &self,
+        _evaluator: &mut Evaluator,
+        arguments: Vec<Rc<Value>>,
+    ) -> Result<Rc<Value>, TullyError> {
+        let value = match &*arguments[0] {
+            Value::Int(value) => Some(*value),
+            // truncates towards zero like a C cast, but refuses to saturate
+            Value::Float(value) => float_to_int(value.trunc()),
+            Value::String(value) => match value.trim().parse::<i64>() {
+                Ok(value) => Some(value),
+                Err(_) => value
+                    .trim()
+                    .parse::<f64>()
+                    .ok()
+                    .and_then(|value| float_to_int(value.trunc())),
+            },
+            #[cfg(feature = "bignum")]
+            Value::BigInt(value) => value.to_i64(),
+            #[cfg(feature = "bignum")]
+            Value::Decimal(value) => value.with_scale(0).to_i64(),
+            _ => None,
+        };
+        match value {
+            Some(value) => Ok(Rc::new(Value::Int(value))),
+            None => Err(not_a_number("int", &arguments[0])),
+        }
+    }
+
+    fn to_string(&self) -> String {
+        String::from("<native fn int>")
+    }
+}
+
+pub struct ToFloat;
+
+impl Callable for ToFloat {
+    fn arity(&self) -> usize {
+        return 1;
+    }
+
+    fn call(
+        &self,
+        _evaluator: &mut Evaluator,
+        arguments: Vec<Rc<Value>>,
+    ) -> Result<Rc<Value>, TullyError> {
+        let value = match &*arguments[0] {
+            Value::Int(value) => Some(*value as f64),
+            Value::Float(value) => Some(*value),
+            Value::String(value) => value.trim().parse::<f64>().ok(),
+            #[cfg(feature = "bignum")]
+            Value::BigInt(value) => value.to_f64(),
+            #[cfg(feature = "bignum")]
+            Value::Decimal(value) => value.to_f64(),
+            _ => None,
+        };
+        match value {
+            Some(value) => Ok(Rc::new(Value::Float(value))),
+            None => Err(not_a_number("float", &arguments[0])),
+        }
+    }
+
+    fn to_string(&self) -> String {
+        String::from("<native fn float>")
+    }
+}
+
+#[cfg(feature = "bignum")]
+pub struct ToBigInt;
+
+#[cfg(feature = "bignum")]
+impl Callable for ToBigInt {
+    fn arity(&self) -> usize {
+        return 1;
+    }
+
+    fn call(
+        &self,
+        _evaluator: &mut Evaluator,
+        arguments: Vec<Rc<Value>>,
+    ) -> Result<Rc<Value>, TullyError> {
+        let value = match &*arguments[0] {
+            Value::Int(value) => Some(BigInt::from(*value)),
+            Value::BigInt(value) => Some(value.clone()),
+            Value::Float(value) if value.is_finite() => BigInt::from_f64(value.trunc()),
+            Value::Decimal(value) => Some(value.with_scale(0).into_bigint_and_exponent().0),
+            Value::String(value) => value.trim().parse::<BigInt>().ok().or_else(|| {
+                value
+                    .trim()
+                    .parse::<BigDecimal>()
+                    .ok()
+                    .map(|value| value.with_scale(0).into_bigint_and_exponent().0)
+            }),
+            _ => None,
+        };
+        match value {
+            Some(value) => Ok(Rc::new(Value::BigInt(value))),
+            None => Err(not_a_number("bigint", &arguments[0])),
+        }
+    }
+
+    fn to_string(&self) -> String {
+        String::from("<native fn bigint>")
+    }
+}
+
+#[cfg(feature = "bignum")]
+pub struct ToDecimal;
+
+#[cfg(feature = "bignum")]
+impl Callable for ToDecimal {
+    fn arity(&self) -> usize {
+        return 1;
+    }
+
+    fn call(
+        &self,
+        _evaluator: &mut Evaluator,
+        arguments: Vec<Rc<Value>>,
+    ) -> Result<Rc<Value>, TullyError> {
+        let value = match &*arguments[0] {
+            Value::Int(value) => Some(BigDecimal::from(*value)),
+            Value::BigInt(value) => Some(BigDecimal::from(value.clone())),
+            // the shortest decimal that reads back as the same float, so
+            // that decimal(0.1) is 0.1 rather than the float's exact value
+            Value::Float(value) if value.is_finite() => format!("{}", value).parse().ok(),
+            Value::Decimal(value) => Some(value.clone()),
+            Value::String(value) => value.trim().parse().ok(),
+            _ => None,
+        };
+        match value {
+            Some(value) => Ok(Rc::new(Value::Decimal(value))),
+            None => Err(not_a_number("decimal", &arguments[0])),
+        }
+    }
+
+    fn to_string(&self) -> String {
+        String::from("<native fn decimal>")
+    }
+}
@@ -4,53 +4,137 @@ use std::collections::HashMap;
 use std::ops::Deref;
 use std::rc::Rc;
 
-use crate::default::{Clock, Random, RandomAlphaNumeric};
+use crate::default::{
+    Clock, Has, Keys, Random, RandomAlphaNumeric, Remove, ToFloat, ToInt, Values,
+};
+#[cfg(feature = "bignum")]
+use crate::default::{ToBigInt, ToDecimal};
+use crate::environment::TullyModule;
 use crate::error::TullyError;
 use crate::evaluator::Evaluator;
 use crate::lexer::token::Token;
+use crate::parser::Slot;
 use crate::value::{TullyFunction, Value};
 
+// A local scope. Variables are stored at the index the resolver gave
+// them, so lookups never hash names.
+#[derive(Debug, Default)]
+pub struct Scope {
+    values: Vec<Rc<Value>>,
+    enclosing: Option<Rc<RefCell<Scope>>>,
+}
+
+impl Scope {
+    pub fn new(values: Vec<Rc<Value>>, enclosing: Option<Rc<RefCell<Scope>>>) -> Scope {
+        Scope { values, enclosing }
+    }
+
+    pub fn define(&mut self, index: usize, value: Rc<Value>) {
+        // a declaration may be skipped at runtime (`if (c) var a = 1;`),
+        // leaving holes before `index`
+        while self.values.len() <= index {
+            self.values.push(Rc::new(Value::Nil));
+        }
+        self.values[index] = value;
+    }
+
+    pub fn get_at(&self, slot: Slot) -> Option<Rc<Value>> {
+        if slot.depth == 0 {
+            return self.values.get(slot.index).map(Rc::clone);
+        }
+        let enclosing = self.enclosing.as_ref()?;
+        enclosing.deref().borrow().get_at(Slot {
+            depth: slot.depth - 1,
+            index: slot.index,
+        })
+    }
+
+    fn assign_at(&mut self, slot: Slot, value: Rc<Value>) -> bool {
+        if slot.depth == 0 {
+            if slot.index >= self.values.len() {
+                return false;
+            }
+            self.values[slot.index] = value;
+            return true;
+        }
+        match &self.enclosing {
+            Some(enclosing) => enclosing.borrow_mut().assign_at(
+                Slot {
+                    depth: slot.depth - 1,
+                    index: slot.index,
+                },
+                value,
+            ),
+            None => false,
+        }
+    }
+}
+
+// Globals are looked up by name, first in the module that is running and
+// then in the builtins shared by all modules; everything else lives in a chain of
+// scopes from the innermost block out to the top level. A function keeps
+// the scope it was declared in alive as its closure and runs its body in
+// a fresh scope enclosed by it, so names resolve lexically however the
+// function escapes.
 #[derive(Debug)]
 pub struct Environment {
-    scopes: Vec<Rc<RefCell<HashMap<String, Rc<Value>>>>>,
+    builtins: HashMap<String, Rc<Value>>,
+    module: Rc<TullyModule>,
+    current: Option<Rc<RefCell<Scope>>>,
 }
 
 impl Environment {
     pub fn new() -> Environment {
         Environment {
-            scopes: vec![Rc::new(RefCell::new(HashMap::new()))],
+            builtins: HashMap::new(),
+            module: Rc::new(TullyModule::new(None)),
+            current: None,
         }
     }
 
     pub fn define(&mut self, name: &String, value: Rc<Value>) {
-        let env = &mut self.scopes[0];
-        let mut env = env.borrow_mut();
-        env.insert(name.clone(), value);
+        self.module.define(name, value);
+    }
+
+    fn define_builtin(&mut self, name: &str, value: Rc<Value>) {
+        self.builtins.insert(String::from(name), value);
+    }
+
+    // defines a variable declared at `slot` by the resolver, or a global
+    pub fn define_at(&mut self, slot: Option<usize>, name: &String, value: Rc<Value>) {
+        match (slot, &self.current) {
+            (Some(index), Some(scope)) => scope.borrow_mut().define(index, value),
+            _ => self.define(name, value),
+        }
     }
 
     pub fn get(&self, name: &String) -> Option<Rc<Value>> {
-        for scope in &self.scopes {
-            let value = scope.borrow();
-            let value = value.get(name);
-            match value {
-                Some(value) => return Some(Rc::clone(value)),
-                None => {
-                    continue;
-                }
-            }
+        self.module
+            .get(name)
+            .or_else(|| self.builtins.get(name).map(Rc::clone))
+    }
+
+    pub fn get_at(&self, slot: Option<Slot>, name: &String) -> Option<Rc<Value>> {
+        match (slot, &self.current) {
+            (Some(slot), Some(scope)) => scope.deref().borrow().get_at(slot),
+            _ => self.get(name),
         }
-        return None;
     }
 
     pub fn assign(&mut self, name: &Token, value: Rc<Value>) -> Result<(), TullyError> {
-        for scope in &self.scopes {
-            if scope.deref().borrow().contains_key(&name.lexeme) {
-                scope
-                    .deref()
-                    .borrow_mut()
-                    .insert(name.lexeme.clone(), Rc::clone(&value));
-                return Ok(());
-            }
+        if self.module.is_constant(&name.lexeme) {
+            return Err(Evaluator::error(
+                name,
+                &format!("Cannot assign to const {}", name.lexeme),
+            ));
+        }
+        if self.module.assign(&name.lexeme, value.clone()) {
+            return Ok(());
+        }
+        // assigning to a builtin shadows it in this module only
+        if self.builtins.contains_key(&name.lexeme) {
+            self.module.define(&name.lexeme, value);
+            return Ok(());
         }
         return Err(Evaluator::error(
             name,
@@ -58,39 +142,124 @@ impl Environment {
         ));
     }
 
+    pub fn assign_at(
+        &mut self,
+        slot: Option<Slot>,
+        name: &Token,
+        value: Rc<Value>,
+    ) -> Result<(), TullyError> {
+        match (slot, &self.current) {
+            (Some(slot), Some(scope)) => {
+                if scope.borrow_mut().assign_at(slot, value) {
+                    return Ok(());
+                }
+                Err(Evaluator::error(
+                    name,
+                    &format!("Undefined variable {}", name.lexeme),
+                ))
+            }
+            _ => self.assign(name, value),
+        }
+    }
+
     pub fn new_env(&mut self) {
-        // println!("creating new");
-        self.scopes.insert(0, Rc::new(RefCell::new(HashMap::new())));
+        let enclosing = self.current.take();
+        self.current = Some(Rc::new(RefCell::new(Scope::new(Vec::new(), enclosing))));
     }
 
-    pub fn import_env(&mut self, environments: &Vec<Rc<RefCell<HashMap<String, Rc<Value>>>>>) {
-        for env in environments {
-            self.scopes.insert(0, Rc::clone(env))
-        }
+    pub fn delete_recent(&mut self) -> Option<Rc<RefCell<Scope>>> {
+        let scope = self.current.take()?;
+        self.current = scope.deref().borrow().enclosing.clone();
+        Some(scope)
+    }
+
+    // the scope a function declared right now closes over
+    pub fn capture(&self) -> Option<Rc<RefCell<Scope>>> {
+        self.current.clone()
+    }
+
+    // makes `scope` the current scope, handing back the one it replaced
+    pub fn replace(&mut self, scope: Option<Rc<RefCell<Scope>>>) -> Option<Rc<RefCell<Scope>>> {
+        std::mem::replace(&mut self.current, scope)
+    }
+
+    // the module whose globals are in use, captured by functions
+    pub fn module(&self) -> Rc<TullyModule> {
+        Rc::clone(&self.module)
     }
 
-    pub fn delete_recent(&mut self) -> Option<Rc<RefCell<HashMap<String, Rc<Value>>>>> {
-        Some(self.scopes.remove(0))
+    // makes `module` the one whose globals are in use, handing back the
+    // one it replaced
+    pub fn replace_module(&mut self, module: Rc<TullyModule>) -> Rc<TullyModule> {
+        std::mem::replace(&mut self.module, module)
     }
 
     pub fn set_default_functions(&mut self) {
-        self.define(
+        self.define_builtin(
             &String::from("clock"),
             Rc::new(Value::Function(TullyFunction::NativeFunction(Rc::new(
                 Clock,
             )))),
         );
-        self.define(
+        self.define_builtin(
             &String::from("random_alphanumeric"),
             Rc::new(Value::Function(TullyFunction::NativeFunction(Rc::new(
                 RandomAlphaNumeric,
             )))),
         );
-        self.define(
+        self.define_builtin(
             &String::from("random"),
             Rc::new(Value::Function(TullyFunction::NativeFunction(Rc::new(
                 Random,
             )))),
         );
+        self.define_builtin(
+            &String::from("int"),
+            Rc::new(Value::Function(TullyFunction::NativeFunction(Rc::new(
+                ToInt,
+            )))),
+        );
+        self.define_builtin(
+            &String::from("float"),
+            Rc::new(Value::Function(TullyFunction::NativeFunction(Rc::new(
+                ToFloat,
+            )))),
+        );
+        #[cfg(feature = "bignum")]
+        self.define_builtin(
+            &String::from("bigint"),
+            Rc::new(Value::Function(TullyFunction::NativeFunction(Rc::new(
+                ToBigInt,
+            )))),
+        );
+        #[cfg(feature = "bignum")]
+        self.define_builtin(
+            &String::from("decimal"),
+            Rc::new(Value::Function(TullyFunction::NativeFunction(Rc::new(
+                ToDecimal,
+            )))),
+        );
+        self.define_builtin(
+            &String::from("keys"),
+            Rc::new(Value::Function(TullyFunction::NativeFunction(Rc::new(
+                Keys,
+            )))),
+        );
+        self.define_builtin(
+            &String::from("values"),
+            Rc::new(Value::Function(TullyFunction::NativeFunction(Rc::new(
+                Values,
+            )))),
+        );
+        self.define_builtin(
+            &String::from("has"),
+            Rc::new(Value::Function(TullyFunction::NativeFunction(Rc::new(Has)))),
+        );
+        self.define_builtin(
+            &String::from("remove"),
+            Rc::new(Value::Function(TullyFunction::NativeFunction(Rc::new(
+                Remove,
+            )))),
+        );
     }
 }
@@ -1,3 +1,6 @@
 pub use environment::Environment;
+pub use environment::Scope;
+pub use module::TullyModule;
 
 mod environment;
+mod module;
@@ -0,0 +1,63 @@
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::rc::Rc;
+
+use crate::value::Value;
+
+// The globals of one script, which are also what it exports. Functions
+// remember the module they were declared in, so they keep seeing its
+// globals when another module calls them.
+#[derive(Debug)]
+pub struct TullyModule {
+    pub path: Option<String>,
+    globals: RefCell<HashMap<String, Rc<Value>>>,
+    constants: RefCell<HashSet<String>>,
+}
+
+impl TullyModule {
+    pub fn new(path: Option<String>) -> TullyModule {
+        TullyModule {
+            path,
+            globals: RefCell::new(HashMap::new()),
+            constants: RefCell::new(HashSet::new()),
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Option<Rc<Value>> {
+        self.globals.borrow().get(name).map(Rc::clone)
+    }
+
+    pub fn define(&self, name: &str, value: Rc<Value>) {
+        self.globals.borrow_mut().insert(String::from(name), value);
+    }
+
+    pub fn define_constant(&self, name: &str, value: Rc<Value>) {
+        self.define(name, value);
+        self.constants.borrow_mut().insert(String::from(name));
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.globals.borrow().contains_key(name)
+    }
+
+    pub fn is_constant(&self, name: &str) -> bool {
+        self.constants.borrow().contains(name)
+    }
+
+    pub fn assign(&self, name: &str, value: Rc<Value>) -> bool {
+        match self.globals.borrow_mut().get_mut(name) {
+            Some(global) => {
+                *global = value;
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        match &self.path {
+            Some(path) => path,
+            None => "main",
+        }
+    }
+}
@@ -3,14 +3,24 @@ use std::fmt::{self, Display, Formatter};
 use std::rc::Rc;
 
 use crate::lexer::token::Token;
-use crate::value::Value;
+use crate::value::{TullyException, Value};
 
 #[derive(Debug)]
 pub enum TullyError {
     RunTimeMessage(String),
     ParserErrorMessage(String),
+    ResolverErrorMessage(String),
     LexicalErrorMessage(String),
+    // a value thrown by `throw` or a runtime error, and the line it was
+    // thrown at; the only error a `catch` intercepts
+    Throw(Rc<Value>, usize),
     Return(Rc<Value>),
+    // a value given by `yield`, suspending the generator it is in
+    Yield(Rc<Value>),
+    // a `?.` found nil, skipping the rest of its optional chain
+    ShortCircuit,
+    Break,
+    Continue,
 }
 
 impl Display for TullyError {
@@ -18,8 +28,22 @@ impl Display for TullyError {
         match self {
             TullyError::RunTimeMessage(value) => write!(f, "Runtime Error : {}", value),
             TullyError::ParserErrorMessage(value) => write!(f, "Parsing Error: {}", value),
+            TullyError::ResolverErrorMessage(value) => write!(f, "Resolving Error: {}", value),
             TullyError::LexicalErrorMessage(value) => write!(f, "Lexical Error: {}", value),
+            TullyError::Throw(value, line) => match &**value {
+                Value::Error(error) => write!(f, "Runtime Error : {}", error.describe()),
+                value => write!(
+                    f,
+                    "Runtime Error : Uncaught {} (line {})",
+                    value.to_string(),
+                    line
+                ),
+            },
             TullyError::Return(value) => write!(f, "Return value {}", value.to_string()),
+            TullyError::Yield(_) => write!(f, "Yield outside of a generator"),
+            TullyError::ShortCircuit => write!(f, "Short circuit outside of an optional chain"),
+            TullyError::Break => write!(f, "Break outside of a loop"),
+            TullyError::Continue => write!(f, "Continue outside of a loop"),
         }
     }
 }
@@ -40,11 +64,13 @@ impl TullyError {
         TullyError::ParserErrorMessage(TullyError::error(token, message))
     }
 
+    pub fn resolver_error_message(token: &Token, message: &str) -> TullyError {
+        TullyError::ResolverErrorMessage(TullyError::error(Some(token), message))
+    }
+
     pub fn runtime_error_message(token: &Token, message: &str) -> TullyError {
-        TullyError::RunTimeMessage(format!(
-            "{} at \"{}\" (line {})",
-            message, token.lexeme, token.line
-        ))
+        let error = TullyException::new(String::from(message), token.line, token.lexeme.clone());
+        TullyError::Throw(Rc::new(Value::Error(Rc::new(error))), token.line)
     }
 }
 
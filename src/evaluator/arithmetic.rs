@@ -0,0 +1,220 @@
+use std::cmp::Ordering;
+use std::convert::TryFrom;
+
+#[cfg(feature = "bignum")]
+use bigdecimal::BigDecimal;
+#[cfg(feature = "bignum")]
+use num_bigint::BigInt;
+
+use crate::error::TullyError;
+#[cfg(feature = "bignum")]
+use crate::evaluator::bignum;
+use crate::evaluator::Evaluator;
+use crate::lexer::token::{Token, TokenType};
+use crate::value::{float_to_int, Value};
+
+// Operands of a numeric operation. Integers stay integers only when both
+// sides are integers, anything mixed with a float is done in floats.
+pub enum Numbers {
+    Int(i64, i64),
+    Float(f64, f64),
+    #[cfg(feature = "bignum")]
+    BigInt(BigInt, BigInt),
+    #[cfg(feature = "bignum")]
+    Decimal(BigDecimal, BigDecimal),
+}
+
+fn numbers(operator: &Token, left: &Value, right: &Value) -> Result<Numbers, TullyError> {
+    match (left, right) {
+        (Value::Int(left), Value::Int(right)) => Ok(Numbers::Int(*left, *right)),
+        (Value::Int(left), Value::Float(right)) => Ok(Numbers::Float(*left as f64, *right)),
+        (Value::Float(left), Value::Int(right)) => Ok(Numbers::Float(*left, *right as f64)),
+        (Value::Float(left), Value::Float(right)) => Ok(Numbers::Float(*left, *right)),
+        #[cfg(feature = "bignum")]
+        (left, right) if left.is_number() && right.is_number() => {
+            bignum::numbers(operator, left, right)
+        }
+        (left, _) if left.is_number() => Err(Evaluator::error(
+            operator,
+            "Expecting number in right side of operation",
+        )),
+        _ => Err(Evaluator::error(
+            operator,
+            "Expecting number in left side of operation",
+        )),
+    }
+}
+
+pub fn arithmetic(operator: &Token, left: &Value, right: &Value) -> Result<Value, TullyError> {
+    match numbers(operator, left, right)? {
+        Numbers::Int(left, right) => int_arithmetic(operator, left, right),
+        Numbers::Float(left, right) => float_arithmetic(operator, left, right),
+        #[cfg(feature = "bignum")]
+        Numbers::BigInt(left, right) => bignum::bigint_arithmetic(operator, left, right),
+        #[cfg(feature = "bignum")]
+        Numbers::Decimal(left, right) => bignum::decimal_arithmetic(operator, left, right),
+    }
+}
+
+fn int_arithmetic(operator: &Token, left: i64, right: i64) -> Result<Value, TullyError> {
+    let value = match operator.tt {
+        TokenType::Plus => left.checked_add(right),
+        TokenType::Minus => left.checked_sub(right),
+        TokenType::Star => left.checked_mul(right),
+        TokenType::Slash => {
+            check_divisor(operator, right)?;
+            return Ok(Value::Float(left as f64 / right as f64));
+        }
+        TokenType::TildeSlash => {
+            check_divisor(operator, right)?;
+            left.checked_div(right).map(|quotient| {
+                if left % right != 0 && (left < 0) != (right < 0) {
+                    quotient - 1
+                } else {
+                    quotient
+                }
+            })
+        }
+        TokenType::Percentage => {
+            check_divisor(operator, right)?;
+            left.checked_rem(right)
+        }
+        TokenType::StarStar => {
+            if right < 0 {
+                return Ok(Value::Float((left as f64).powf(right as f64)));
+            }
+            u32::try_from(right)
+                .ok()
+                .and_then(|right| left.checked_pow(right))
+        }
+        _ => return Err(Evaluator::error(operator, "Unsupported binary operation")),
+    };
+    match value {
+        Some(value) => Ok(Value::Int(value)),
+        None => Err(Evaluator::error(operator, "Integer overflow")),
+    }
+}
+
+fn float_arithmetic(operator: &Token, left: f64, right: f64) -> Result<Value, TullyError> {
+    let value = match operator.tt {
+        TokenType::Plus => left + right,
+        TokenType::Minus => left - right,
+        TokenType::Star => left * right,
+        TokenType::Slash => left / right,
+        TokenType::TildeSlash => (left / right).floor(),
+        TokenType::Percentage => left % right,
+        TokenType::StarStar => left.powf(right),
+        _ => return Err(Evaluator::error(operator, "Unsupported binary operation")),
+    };
+    Ok(Value::Float(value))
+}
+
+fn check_divisor(operator: &Token, divisor: i64) -> Result<(), TullyError> {
+    if divisor == 0 {
+        return Err(Evaluator::error(operator, "Division by zero"));
+    }
+    Ok(())
+}
+
+fn ordering(operator: &Token, left: &Value, right: &Value) -> Result<Option<Ordering>, TullyError> {
+    #[cfg(feature = "bignum")]
+    if let Some(ordering) = bignum::exact_ordering(left, right) {
+        return Ok(ordering);
+    }
+    Ok(match numbers(operator, left, right)? {
+        Numbers::Int(left, right) => Some(left.cmp(&right)),
+        Numbers::Float(left, right) => left.partial_cmp(&right),
+        #[cfg(feature = "bignum")]
+        Numbers::BigInt(left, right) => Some(left.cmp(&right)),
+        #[cfg(feature = "bignum")]
+        Numbers::Decimal(left, right) => Some(left.cmp(&right)),
+    })
+}
+
+pub fn compare(operator: &Token, left: &Value, right: &Value) -> Result<bool, TullyError> {
+    let ordering = match ordering(operator, left, right)? {
+        Some(ordering) => ordering,
+        // NaN is neither smaller nor bigger than anything
+        None => return Ok(false),
+    };
+    match operator.tt {
+        TokenType::Greater => Ok(ordering == Ordering::Greater),
+        TokenType::GreaterEqual => Ok(ordering != Ordering::Less),
+        TokenType::Lesser => Ok(ordering == Ordering::Less),
+        TokenType::LesserEqual => Ok(ordering != Ordering::Greater),
+        _ => Err(Evaluator::error(operator, "Unsupported comparison")),
+    }
+}
+
+pub fn negate(operator: &Token, value: &Value) -> Result<Value, TullyError> {
+    match value {
+        Value::Int(value) => match value.checked_neg() {
+            Some(value) => Ok(Value::Int(value)),
+            None => Err(Evaluator::error(operator, "Integer overflow")),
+        },
+        Value::Float(value) => Ok(Value::Float(-value)),
+        #[cfg(feature = "bignum")]
+        Value::BigInt(value) => Ok(Value::BigInt(-value)),
+        #[cfg(feature = "bignum")]
+        Value::Decimal(value) => Ok(Value::Decimal(-value)),
+        _ => Err(Evaluator::error(
+            operator,
+            "Expecting number in unary operation",
+        )),
+    }
+}
+
+// An operand of a bitwise operation, which has to be a whole number
+fn integer(operator: &Token, value: &Value) -> Result<i64, TullyError> {
+    match value {
+        Value::Int(value) => Ok(*value),
+        Value::Float(value) => match float_to_int(*value) {
+            Some(value) => Ok(value),
+            None => Err(Evaluator::error(
+                operator,
+                &format!("Bitwise operands must be whole numbers, got {}", value),
+            )),
+        },
+        _ => Err(Evaluator::error(
+            operator,
+            "Expecting integers in bitwise operation",
+        )),
+    }
+}
+
+fn shift(operator: &Token, amount: i64) -> Result<u32, TullyError> {
+    match u32::try_from(amount) {
+        Ok(amount) if amount < 64 => Ok(amount),
+        _ => Err(Evaluator::error(
+            operator,
+            "Shift amount must be between 0 and 63",
+        )),
+    }
+}
+
+// `&`, `|`, `^`, `<<` and `>>` on the bits of two's complement integers.
+// Bits shifted past either end are dropped and `>>` keeps the sign.
+pub fn bitwise(operator: &Token, left: &Value, right: &Value) -> Result<Value, TullyError> {
+    #[cfg(feature = "bignum")]
+    if let Some(value) = bignum::bitwise(operator, left, right) {
+        return value;
+    }
+    let (left, right) = (integer(operator, left)?, integer(operator, right)?);
+    let value = match operator.tt {
+        TokenType::Ampersand => left & right,
+        TokenType::Pipe => left | right,
+        TokenType::Caret => left ^ right,
+        TokenType::LessLess => left << shift(operator, right)?,
+        TokenType::GreaterGreater => left >> shift(operator, right)?,
+        _ => return Err(Evaluator::error(operator, "Unsupported binary operation")),
+    };
+    Ok(Value::Int(value))
+}
+
+pub fn complement(operator: &Token, value: &Value) -> Result<Value, TullyError> {
+    #[cfg(feature = "bignum")]
+    if let Some(value) = bignum::complement(operator, value) {
+        return value;
+    }
+    Ok(Value::Int(!integer(operator, value)?))
+}
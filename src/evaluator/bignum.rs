@@ -0,0 +1,338 @@
+use std::cmp::Ordering;
+use std::convert::TryFrom;
+
+use bigdecimal::BigDecimal;
+use num_bigint::BigInt;
+use num_traits::{One, Signed, ToPrimitive, Zero};
+
+use crate::error::TullyError;
+use crate::evaluator::arithmetic::Numbers;
+use crate::evaluator::Evaluator;
+use crate::lexer::token::{Token, TokenType};
+use crate::value::{MapKey, Value};
+
+fn is_big(value: &Value) -> bool {
+    matches!(value, Value::BigInt(_) | Value::Decimal(_))
+}
+
+fn to_bigint(value: &Value) -> BigInt {
+    match value {
+        Value::Int(value) => BigInt::from(*value),
+        Value::BigInt(value) => value.clone(),
+        _ => unreachable!("only integers are promoted to big integers"),
+    }
+}
+
+fn to_decimal(value: &Value) -> BigDecimal {
+    match value {
+        Value::Int(value) => BigDecimal::from(*value),
+        Value::BigInt(value) => BigDecimal::from(value.clone()),
+        Value::Decimal(value) => value.clone(),
+        _ => unreachable!("floats are never promoted to decimals"),
+    }
+}
+
+// The exact value of a finite float. Every float is a fraction with a power
+// of two as denominator, which always has a terminating decimal expansion.
+pub fn exact_float(value: f64) -> Option<BigDecimal> {
+    if !value.is_finite() {
+        return None;
+    }
+    let bits = value.to_bits();
+    let exponent = ((bits >> 52) & 0x7ff) as i64;
+    let mut mantissa = bits & ((1 << 52) - 1);
+    let exponent = if exponent == 0 {
+        -1074
+    } else {
+        mantissa |= 1 << 52;
+        exponent - 1075
+    };
+    let mut digits = BigInt::from(mantissa);
+    if bits >> 63 == 1 {
+        digits = -digits;
+    }
+    if exponent >= 0 {
+        return Some(BigDecimal::from(digits << exponent as usize));
+    }
+    // m * 2^-e == m * 5^e / 10^e
+    let scale = -exponent;
+    Some(BigDecimal::new(
+        digits * BigInt::from(5).pow(scale as u32),
+        scale,
+    ))
+}
+
+// Promotes the operands of an arithmetic operation when at least one of
+// them is big. Floats are approximate, so they are not silently mixed
+// into exact results.
+pub fn numbers(operator: &Token, left: &Value, right: &Value) -> Result<Numbers, TullyError> {
+    match (left, right) {
+        (Value::Float(_), Value::Decimal(_)) | (Value::Decimal(_), Value::Float(_)) => {
+            Err(Evaluator::error(
+                operator,
+                "Cannot mix float and decimal numbers, convert with decimal()",
+            ))
+        }
+        (Value::Float(_), _) | (_, Value::Float(_)) => Err(Evaluator::error(
+            operator,
+            "Cannot mix float and big integer numbers, convert with bigint() or float()",
+        )),
+        (Value::Decimal(_), _) | (_, Value::Decimal(_)) => {
+            Ok(Numbers::Decimal(to_decimal(left), to_decimal(right)))
+        }
+        _ => Ok(Numbers::BigInt(to_bigint(left), to_bigint(right))),
+    }
+}
+
+// Exact ordering of two numbers when at least one of them is big, `None`
+// when the plain int and float rules apply instead.
+pub fn exact_ordering(left: &Value, right: &Value) -> Option<Option<Ordering>> {
+    if !(left.is_number() && right.is_number() && (is_big(left) || is_big(right))) {
+        return None;
+    }
+    let exact = |value: &Value| -> Result<BigDecimal, Option<Ordering>> {
+        match value {
+            Value::Float(value) if value.is_nan() => Err(None),
+            Value::Float(value) if value.is_infinite() => Err(Some(if *value > 0.0 {
+                Ordering::Greater
+            } else {
+                Ordering::Less
+            })),
+            Value::Float(value) => Ok(exact_float(*value).unwrap()),
+            value => Ok(to_decimal(value)),
+        }
+    };
+    Some(match (exact(left), exact(right)) {
+        (Ok(left), Ok(right)) => Some(left.cmp(&right)),
+        (Err(None), _) | (_, Err(None)) => None,
+        (Err(ordering), _) => ordering,
+        (_, Err(ordering)) => ordering.map(Ordering::reverse),
+    })
+}
+
+// Map key of a big number or of a float that is not a small integer. All
+// numbers that are equal share a key: integers are keyed as `Int` when they
+// fit, otherwise as `BigInt`, and fractions by their normalized decimal.
+pub fn key(value: &Value) -> Option<MapKey> {
+    let decimal = match value {
+        Value::Float(value) => exact_float(*value)?,
+        Value::BigInt(value) => return Some(integer_key(value.clone())),
+        Value::Decimal(value) => value.clone(),
+        _ => return None,
+    };
+    if decimal.is_integer() {
+        let (digits, scale) = decimal.with_scale(0).into_bigint_and_exponent();
+        debug_assert_eq!(scale, 0);
+        return Some(integer_key(digits));
+    }
+    Some(MapKey::Decimal(decimal.normalized()))
+}
+
+fn integer_key(value: BigInt) -> MapKey {
+    match value.to_i64() {
+        Some(value) => MapKey::Int(value),
+        None => MapKey::BigInt(value),
+    }
+}
+
+fn check_divisor<T: Zero>(operator: &Token, divisor: &T) -> Result<(), TullyError> {
+    if divisor.is_zero() {
+        return Err(Evaluator::error(operator, "Division by zero"));
+    }
+    Ok(())
+}
+
+fn exponent(operator: &Token, exponent: &BigInt) -> Result<u32, TullyError> {
+    match u32::try_from(exponent.abs()) {
+        Ok(exponent) => Ok(exponent),
+        Err(_) => Err(Evaluator::error(operator, "Exponent is too large")),
+    }
+}
+
+pub fn bigint_arithmetic(
+    operator: &Token,
+    left: BigInt,
+    right: BigInt,
+) -> Result<Value, TullyError> {
+    let value = match operator.tt {
+        TokenType::Plus => left + right,
+        TokenType::Minus => left - right,
+        TokenType::Star => left * right,
+        TokenType::Slash => {
+            check_divisor(operator, &right)?;
+            return Ok(Value::Decimal(
+                BigDecimal::from(left) / BigDecimal::from(right),
+            ));
+        }
+        TokenType::TildeSlash => {
+            check_divisor(operator, &right)?;
+            floor_div(&left, &right)
+        }
+        TokenType::Percentage => {
+            check_divisor(operator, &right)?;
+            left % right
+        }
+        TokenType::StarStar => {
+            let power = left.pow(exponent(operator, &right)?);
+            if right.is_negative() {
+                check_divisor(operator, &power)?;
+                return Ok(Value::Decimal(BigDecimal::one() / BigDecimal::from(power)));
+            }
+            power
+        }
+        _ => return Err(Evaluator::error(operator, "Unsupported binary operation")),
+    };
+    Ok(Value::BigInt(value))
+}
+
+fn floor_div(left: &BigInt, right: &BigInt) -> BigInt {
+    let quotient = left / right;
+    if !(left % right).is_zero() && left.is_negative() != right.is_negative() {
+        quotient - 1
+    } else {
+        quotient
+    }
+}
+
+// Both operands as integers of the same scale, which is exact because
+// raising the scale of a decimal only appends zeros.
+fn same_scale(left: &BigDecimal, right: &BigDecimal) -> (BigInt, BigInt, i64) {
+    let scale = left
+        .as_bigint_and_exponent()
+        .1
+        .max(right.as_bigint_and_exponent().1);
+    let (left, _) = left.with_scale(scale).into_bigint_and_exponent();
+    let (right, _) = right.with_scale(scale).into_bigint_and_exponent();
+    (left, right, scale)
+}
+
+pub fn decimal_arithmetic(
+    operator: &Token,
+    left: BigDecimal,
+    right: BigDecimal,
+) -> Result<Value, TullyError> {
+    let value = match operator.tt {
+        TokenType::Plus => left + right,
+        TokenType::Minus => left - right,
+        TokenType::Star => left * right,
+        TokenType::Slash => {
+            check_divisor(operator, &right)?;
+            left / right
+        }
+        TokenType::TildeSlash => {
+            check_divisor(operator, &right)?;
+            let (left, right, _) = same_scale(&left, &right);
+            BigDecimal::from(floor_div(&left, &right))
+        }
+        TokenType::Percentage => {
+            check_divisor(operator, &right)?;
+            let (left, right, scale) = same_scale(&left, &right);
+            BigDecimal::new(left % right, scale)
+        }
+        TokenType::StarStar => {
+            if !right.is_integer() {
+                return Err(Evaluator::error(
+                    operator,
+                    "Decimals can only be raised to integer powers",
+                ));
+            }
+            let (power, _) = right.with_scale(0).into_bigint_and_exponent();
+            let mut remaining = exponent(operator, &power)?;
+            let mut base = left;
+            let mut value = BigDecimal::one();
+            while remaining > 0 {
+                if remaining & 1 == 1 {
+                    value = &value * &base;
+                }
+                remaining >>= 1;
+                if remaining > 0 {
+                    base = &base * &base;
+                }
+            }
+            if power.is_negative() {
+                check_divisor(operator, &value)?;
+                value = BigDecimal::one() / value;
+            }
+            value
+        }
+        _ => return Err(Evaluator::error(operator, "Unsupported binary operation")),
+    };
+    Ok(Value::Decimal(value))
+}
+
+// A whole number operand of a bitwise operation on big integers
+fn integer(operator: &Token, value: &Value) -> Result<BigInt, TullyError> {
+    let decimal = match value {
+        Value::Int(value) => return Ok(BigInt::from(*value)),
+        Value::BigInt(value) => return Ok(value.clone()),
+        Value::Float(value) => exact_float(*value),
+        Value::Decimal(value) => Some(value.clone()),
+        _ => {
+            return Err(Evaluator::error(
+                operator,
+                "Expecting integers in bitwise operation",
+            ))
+        }
+    };
+    match decimal {
+        Some(decimal) if decimal.is_integer() => {
+            Ok(decimal.with_scale(0).into_bigint_and_exponent().0)
+        }
+        _ => Err(Evaluator::error(
+            operator,
+            &format!(
+                "Bitwise operands must be whole numbers, got {}",
+                value.to_string()
+            ),
+        )),
+    }
+}
+
+// Bitwise operations when at least one operand is big, `None` when the
+// plain integer rules apply instead. Big integers have no width, so
+// nothing is shifted out on the left.
+pub fn bitwise(operator: &Token, left: &Value, right: &Value) -> Option<Result<Value, TullyError>> {
+    if !(is_big(left) || is_big(right)) {
+        return None;
+    }
+    let operands = integer(operator, left).and_then(|left| Ok((left, integer(operator, right)?)));
+    let (left, right) = match operands {
+        Ok(operands) => operands,
+        Err(err) => return Some(Err(err)),
+    };
+    let value = match operator.tt {
+        TokenType::Ampersand => left & right,
+        TokenType::Pipe => left | right,
+        TokenType::Caret => left ^ right,
+        TokenType::LessLess | TokenType::GreaterGreater => {
+            let amount = match u32::try_from(&right) {
+                Ok(amount) => amount,
+                Err(_) => {
+                    return Some(Err(Evaluator::error(
+                        operator,
+                        "Shift amount must be between 0 and 4294967295",
+                    )))
+                }
+            };
+            if let TokenType::LessLess = operator.tt {
+                left << amount
+            } else {
+                left >> amount
+            }
+        }
+        _ => {
+            return Some(Err(Evaluator::error(
+                operator,
+                "Unsupported binary operation",
+            )))
+        }
+    };
+    Some(Ok(Value::BigInt(value)))
+}
+
+pub fn complement(operator: &Token, value: &Value) -> Option<Result<Value, TullyError>> {
+    if !is_big(value) {
+        return None;
+    }
+    Some(integer(operator, value).map(|value| Value::BigInt(!value)))
+}
@@ -1,15 +1,21 @@
-use std::borrow::Borrow;
 use std::cell::RefCell;
-use std::collections::HashMap;
+use std::ops::Deref;
 use std::rc::Rc;
 
+use crate::environment::{Scope, TullyModule};
 use crate::error::TullyError;
+use crate::evaluator::generator::Generator;
 use crate::evaluator::Evaluator;
-use crate::parser::Function;
-use crate::value::{TullyFunction, Value};
+use crate::parser::{Function, Param, Slot};
+use crate::value::Value;
 
 pub trait Callable {
+    // the fewest arguments accepted
     fn arity(&self) -> usize;
+    // the most arguments accepted, `None` when there is no limit
+    fn max_arity(&self) -> Option<usize> {
+        Some(self.arity())
+    }
     fn call(
         &self,
         evaluator: &mut Evaluator,
@@ -20,34 +26,69 @@ pub trait Callable {
 
 #[derive(Clone)]
 pub struct TullyCallable {
-    pub declaration: Function,
-    pub closure: Option<Vec<Rc<RefCell<HashMap<String, Rc<Value>>>>>>,
+    pub declaration: Rc<Function>,
+    pub closure: Option<Rc<RefCell<Scope>>>,
+    // the module whose globals the body sees
+    pub module: Rc<TullyModule>,
+    pub is_initializer: bool,
 }
 
 impl TullyCallable {
-    pub fn add_closure(&mut self, closure: Rc<RefCell<HashMap<String, Rc<Value>>>>) {
-        if let Some(closures) = &mut self.closure {
-            closures.push(closure);
-            return;
+    pub fn new(
+        declaration: Rc<Function>,
+        closure: Option<Rc<RefCell<Scope>>>,
+        module: Rc<TullyModule>,
+        is_initializer: bool,
+    ) -> TullyCallable {
+        TullyCallable {
+            declaration,
+            closure,
+            module,
+            is_initializer,
         }
-        self.closure = Some(vec![closure]);
     }
 
-    pub fn add_closure_others(&mut self, closures: &Vec<Rc<RefCell<HashMap<String, Rc<Value>>>>>) {
-        if let None = &self.closure {
-            self.closure = Some(vec![]);
-        }
-        if let Some(closures_2) = &mut self.closure {
-            for closure in closures {
-                closures_2.push(Rc::clone(closure));
-            }
+    // defines the parameters without an argument, in order so that a
+    // default can use the parameters before it
+    fn bind_missing(
+        &self,
+        evaluator: &mut Evaluator,
+        given: usize,
+        rest: Option<Rc<Value>>,
+    ) -> Result<(), TullyError> {
+        let mut rest = rest;
+        for (index, param) in self.declaration.params.iter().enumerate().skip(given) {
+            let value = match &param.default {
+                Some(default) => evaluator.accept(default)?,
+                None if param.rest => rest.take().unwrap(),
+                None => continue,
+            };
+            evaluator
+                .globals
+                .define_at(Some(index), &param.name.lexeme, value);
         }
+        Ok(())
+    }
+
+    // methods see `this` through one more scope holding the instance
+    pub fn bind(&self, instance: Rc<Value>) -> TullyCallable {
+        let scope = Scope::new(vec![instance], self.closure.clone());
+        TullyCallable::new(
+            Rc::clone(&self.declaration),
+            Some(Rc::new(RefCell::new(scope))),
+            Rc::clone(&self.module),
+            self.is_initializer,
+        )
     }
 }
 
 impl Callable for TullyCallable {
     fn arity(&self) -> usize {
-        self.declaration.params.len()
+        self.declaration.arity().0
+    }
+
+    fn max_arity(&self) -> Option<usize> {
+        self.declaration.arity().1
     }
 
     fn call(
@@ -55,47 +96,48 @@ impl Callable for TullyCallable {
         evaluator: &mut Evaluator,
         arguments: Vec<Rc<Value>>,
     ) -> Result<Rc<Value>, TullyError> {
-        // println!("calling");
-        let mut n = 0;
-        if let Some(value) = &self.closure {
-            n = value.len();
-            evaluator.globals.import_env(value);
+        let params = &self.declaration.params;
+        let mut arguments = arguments;
+        // the rest parameter collects the arguments past the other parameters
+        let mut rest = None;
+        if let Some(Param { rest: true, .. }) = params.last() {
+            let extra = arguments.split_off(arguments.len().min(params.len() - 1));
+            rest = Some(Rc::new(Value::List(Rc::new(RefCell::new(extra)))));
         }
-        evaluator.globals.new_env();
-        //  println!("env create {:?}", evaluator.globals.locals);
-        for (i, param) in self.declaration.params.iter().enumerate() {
-            evaluator
-                .globals
-                .define(&param.lexeme, Rc::clone(&arguments[i]));
+        let given = arguments.len();
+        let scope = Scope::new(arguments, self.closure.clone());
+        let module = evaluator.globals.replace_module(Rc::clone(&self.module));
+        let previous = evaluator
+            .globals
+            .replace(Some(Rc::new(RefCell::new(scope))));
+        let bound = self.bind_missing(evaluator, given, rest);
+        if self.declaration.generator {
+            // the body waits for the first `next`
+            let scope = evaluator.globals.replace(previous);
+            evaluator.globals.replace_module(module);
+            bound?;
+            let generator = Generator::new(self.clone(), scope.unwrap());
+            return Ok(Rc::new(Value::Iterator(Rc::new(RefCell::new(generator)))));
         }
-        // println!("env {:#?}", evaluator.globals);
-        let value = evaluator.execute_block(&self.declaration.body.statements, false);
-        if let Some(env) = evaluator.globals.delete_recent() {
-            // println!("n {}", n);
-            for _i in 0..n {
-                evaluator.globals.delete_recent();
-            }
-
-            if let Err(err) = value {
-                if let TullyError::Return(value) = err {
-                    if let Value::Function(tf) = value.borrow() {
-                        if let TullyFunction::NFunction(tc) = tf {
-                            //println!("print");
-                            // println!("{:?}", env);
-                            let t = tc.clone();
-                            if let Some(closures) = &self.closure {
-                                t.borrow_mut().add_closure_others(closures);
-                            }
-                            t.borrow_mut().add_closure(env);
-                            return Ok(Rc::new(Value::Function(TullyFunction::NFunction(t))));
-                        }
+        let value =
+            bound.and_then(|_| evaluator.execute_block(&self.declaration.body.statements, false));
+        evaluator.globals.replace(previous);
+        evaluator.globals.replace_module(module);
+        if self.is_initializer {
+            if let Ok(_) | Err(TullyError::Return(_)) = value {
+                if let Some(closure) = &self.closure {
+                    let this = Slot { depth: 0, index: 0 };
+                    if let Some(this) = closure.deref().borrow().get_at(this) {
+                        return Ok(this);
                     }
-                    return Ok(value);
                 }
-                return Err(err);
             }
         }
-        return Ok(Rc::clone(&evaluator.constants.nil));
+        match value {
+            Ok(_) => Ok(Rc::clone(&evaluator.constants.nil)),
+            Err(TullyError::Return(value)) => Ok(value),
+            Err(err) => Err(err),
+        }
     }
 
     fn to_string(&self) -> String {
@@ -0,0 +1,89 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use crate::error::TullyError;
+use crate::evaluator::{Callable, Evaluator, TullyCallable};
+use crate::value::{TullyFunction, Value};
+
+pub struct TullyClass {
+    pub name: String,
+    pub superclass: Option<Rc<TullyClass>>,
+    pub methods: HashMap<String, TullyCallable>,
+}
+
+pub struct TullyInstance {
+    pub class: Rc<TullyClass>,
+    pub fields: HashMap<String, Rc<Value>>,
+}
+
+impl TullyClass {
+    pub fn find_method(&self, name: &str) -> Option<&TullyCallable> {
+        match self.methods.get(name) {
+            Some(method) => Some(method),
+            None => self.superclass.as_ref()?.find_method(name),
+        }
+    }
+}
+
+impl TullyInstance {
+    pub fn new(class: Rc<TullyClass>) -> TullyInstance {
+        TullyInstance {
+            class,
+            fields: HashMap::new(),
+        }
+    }
+
+    // fields shadow methods; methods come back bound to `instance`
+    pub fn get(&self, name: &str, instance: &Rc<Value>) -> Option<Rc<Value>> {
+        if let Some(value) = self.fields.get(name) {
+            return Some(Rc::clone(value));
+        }
+        let method = self.class.find_method(name)?;
+        Some(Rc::new(Value::Function(TullyFunction::NFunction(Rc::new(
+            RefCell::new(method.bind(Rc::clone(instance))),
+        )))))
+    }
+
+    pub fn set(&mut self, name: &str, value: Rc<Value>) {
+        self.fields.insert(String::from(name), value);
+    }
+}
+
+// Calling a class constructs an instance and runs `init` on it, so the
+// arity of a class is the arity of its initializer.
+impl Callable for Rc<TullyClass> {
+    fn arity(&self) -> usize {
+        match self.find_method("init") {
+            Some(initializer) => initializer.arity(),
+            None => 0,
+        }
+    }
+
+    fn max_arity(&self) -> Option<usize> {
+        match self.find_method("init") {
+            Some(initializer) => initializer.max_arity(),
+            None => Some(0),
+        }
+    }
+
+    fn call(
+        &self,
+        evaluator: &mut Evaluator,
+        arguments: Vec<Rc<Value>>,
+    ) -> Result<Rc<Value>, TullyError> {
+        let instance = Rc::new(Value::Instance(Rc::new(RefCell::new(TullyInstance::new(
+            Rc::clone(self),
+        )))));
+        if let Some(initializer) = self.find_method("init") {
+            initializer
+                .bind(Rc::clone(&instance))
+                .call(evaluator, arguments)?;
+        }
+        Ok(instance)
+    }
+
+    fn to_string(&self) -> String {
+        format!("<class {}>", self.name)
+    }
+}
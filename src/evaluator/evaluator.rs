@@ -1,38 +1,81 @@
 use std::borrow::Borrow;
 use std::cell::RefCell;
+use std::collections::HashMap;
+use std::io::{self, Write};
 use std::rc::Rc;
 
-use crate::environment::Environment;
+use crate::environment::{Environment, Scope, TullyModule};
 use crate::error::TullyError;
+use crate::evaluator::arithmetic;
 use crate::evaluator::callable::TullyCallable;
-use crate::evaluator::Callable;
+use crate::evaluator::generator::{Resume, TryStage};
+use crate::evaluator::iterator::{iterator, next, NextMethod};
+use crate::evaluator::{Callable, TullyClass, TullyInstance};
 use crate::lexer::token::{Token, TokenType};
+use crate::loader::{FileLoader, ModuleLoader};
 use crate::parser::{
-    Assign, Binary, Block, Call, ExprType, Expression, Function, Group, IfStatement, Literal,
-    Parser, Print, Return, Unary, Var, Variable, Visitor, WhileStatement,
+    Assign, Binary, Block, Break, Call, Catch, Class, Compound, Conditional, Continue, ExprType,
+    Expression, ForIn, Function, Get, Group, IfStatement, Import, Index, List, Literal, Logical,
+    Map, Match, MatchArm, OptionalChain, Parser, Pattern, Print, Range, Return, Set, SetIndex,
+    Slot, Spread, Super, This, Throw, Try, Unary, Var, Variable, Visitor, WhileStatement, Yield,
 };
-use crate::value::{Constants, Value};
+use crate::resolver::Resolver;
+use crate::value::{Constants, MapKey, TullyMap, TullyRange, Value};
 use crate::value::{LiteralValue, TullyFunction};
 
-#[derive(Debug)]
 pub struct Evaluator {
     pub constants: Constants,
     pub globals: Environment,
+    output: Box<dyn Write>,
+    loader: Box<dyn ModuleLoader>,
+    // modules that finished running, by the name the loader resolved
+    modules: HashMap<String, Rc<TullyModule>>,
+    // names of the modules being imported, innermost last
+    importing: Vec<String>,
+    // the frames of a suspending generator while a yield unwinds, or of a
+    // resuming one until its statements are re-entered
+    pub(crate) frames: Vec<Resume>,
 }
 
 impl<'a> Evaluator {
     pub fn new() -> Evaluator {
+        Evaluator::with_output(Box::new(io::stdout()))
+    }
+
+    // `print` statements write to `output` instead of stdout
+    pub fn with_output(output: Box<dyn Write>) -> Evaluator {
         let mut env = Environment::new();
         env.set_default_functions();
         Evaluator {
             constants: Constants::new(),
             globals: env,
+            output,
+            loader: Box::new(FileLoader),
+            modules: HashMap::new(),
+            importing: Vec::new(),
+            frames: Vec::new(),
         }
     }
 
+    // imports are found through `loader` instead of the file system
+    pub fn set_loader(&mut self, loader: Box<dyn ModuleLoader>) {
+        self.loader = loader;
+    }
+
+    // path of the script being run, imports in it are relative to it.
+    // The script is named the way the loader names modules and counts as
+    // being imported, so a module importing it back is a cycle.
+    pub fn set_path(&mut self, path: &str) {
+        let name = self.loader.resolve(None, path);
+        let module = TullyModule::new(Some(name.clone()));
+        self.globals.replace_module(Rc::new(module));
+        self.importing = vec![name];
+    }
+
     pub fn eval(&mut self, source: &str) -> Result<(), TullyError> {
         let mut parser = Parser::new(String::from(source.trim()));
         let ast = parser.parse()?;
+        Resolver::new().resolve(&ast)?;
         // println!("{:?}", statements);
         for statement in ast {
             self.execute(&statement)?;
@@ -63,36 +106,81 @@ impl<'a> Evaluator {
             ExprType::Call(call) => self.visit_call(call),
             ExprType::Function(function) => self.visit_function(function),
             ExprType::Return(return_statement) => self.visit_return(return_statement),
+            ExprType::Logical(logical) => self.visit_logical(logical),
+            ExprType::Class(class) => self.visit_class(class),
+            ExprType::Get(get) => self.visit_get(get),
+            ExprType::Set(set) => self.visit_set(set),
+            ExprType::This(this) => self.visit_this(this),
+            ExprType::Super(super_expr) => self.visit_super(super_expr),
+            ExprType::List(list) => self.visit_list(list),
+            ExprType::Index(index) => self.visit_index(index),
+            ExprType::SetIndex(set_index) => self.visit_set_index(set_index),
+            ExprType::Map(map) => self.visit_map(map),
+            ExprType::Break(break_statement) => self.visit_break(break_statement),
+            ExprType::Continue(continue_statement) => self.visit_continue(continue_statement),
+            ExprType::Lambda(lambda) => self.visit_lambda(lambda),
+            ExprType::Throw(throw) => self.visit_throw(throw),
+            ExprType::Try(try_statement) => self.visit_try(try_statement),
+            ExprType::Import(import) => self.visit_import(import),
+            ExprType::Match(match_expr) => self.visit_match(match_expr),
+            ExprType::Compound(compound) => self.visit_compound(compound),
+            ExprType::Conditional(conditional) => self.visit_conditional(conditional),
+            ExprType::Spread(spread) => self.visit_spread(spread),
+            ExprType::Range(range) => self.visit_range(range),
+            ExprType::ForIn(for_in) => self.visit_for_in(for_in),
+            ExprType::Yield(yield_statement) => self.visit_yield(yield_statement),
+            ExprType::OptionalChain(chain) => self.visit_optional_chain(chain),
         }
     }
 
-    fn check_numbers(left: &Value, right: &Value) -> Result<(f64, f64), TullyError> {
-        let left_value = match left {
-            Value::Float(left_val) => left_val,
-            _ => {
-                return Err(TullyError::RunTimeMessage(String::from(
-                    "Expecting float in left side of operation",
-                )));
-            }
-        };
-        let right_value = match right {
-            Value::Float(right_value) => right_value,
-            _ => {
-                return Err(TullyError::RunTimeMessage(String::from(
-                    "Expecting number in right side of operation",
-                )));
-            }
+    fn list_index(bracket: &Token, index: &Value, length: usize) -> Result<usize, TullyError> {
+        let index = match index {
+            Value::Int(index) => *index,
+            Value::Float(index) if index.fract() == 0.0 => *index as i64,
+            _ => return Err(Evaluator::error(bracket, "List index must be an integer")),
         };
-        return Ok((left_value.to_owned(), right_value.to_owned()));
+        if index < 0 {
+            return Err(Evaluator::error(
+                bracket,
+                &format!("Negative list index {}", index),
+            ));
+        }
+        if index as u64 >= length as u64 {
+            return Err(Evaluator::error(
+                bracket,
+                &format!("List index {} out of range for length {}", index, length),
+            ));
+        }
+        Ok(index as usize)
     }
 
-    fn check_number(value: &Value) -> Result<f64, TullyError> {
-        match value {
-            Value::Float(value) => Ok(value.to_owned()),
-            _ => Err(TullyError::RunTimeMessage(String::from(
-                "Expecting number in unary operation",
-            ))),
+    fn map_key(token: &Token, key: &Value) -> Result<MapKey, TullyError> {
+        match key.to_key() {
+            Some(key) => Ok(key),
+            None => Err(Evaluator::error(
+                token,
+                &format!(
+                    "Map key must be a string, number or boolean, got {}",
+                    key.to_string()
+                ),
+            )),
+        }
+    }
+
+    fn check_arity(paren: &Token, callable: &dyn Callable, count: usize) -> Result<(), TullyError> {
+        let (min, max) = (callable.arity(), callable.max_arity());
+        if count >= min && max.is_none_or(|max| count <= max) {
+            return Ok(());
         }
+        let expected = match max {
+            Some(max) if max == min => format!("{}", min),
+            Some(max) => format!("{} to {}", min, max),
+            None => format!("at least {}", min),
+        };
+        Err(Evaluator::error(
+            paren,
+            &format!("Expected {} args but got {}", expected, count),
+        ))
     }
 
     fn is_trusty(obj: &Value) -> bool {
@@ -108,92 +196,239 @@ impl<'a> Evaluator {
         statements: &Vec<Box<ExprType>>,
         new_block: bool,
     ) -> Result<(), TullyError> {
-        if new_block {
-            self.globals.new_env();
+        // a resumed generator continues at the statement it yielded in
+        let mut start = 0;
+        match self.frames.pop() {
+            Some(Resume::Block(index, scope)) => {
+                start = index;
+                if new_block {
+                    self.globals.replace(scope);
+                }
+            }
+            Some(_) => unreachable!("a block resumes from a block frame"),
+            None if new_block => self.globals.new_env(),
+            None => (),
         }
-        for statement in statements {
-            self.execute(&*statement)?;
+        // the scope has to be dropped even when a return, break or error
+        // unwinds through this block
+        let mut result = Ok(());
+        let mut yielded = None;
+        for (index, statement) in statements.iter().enumerate().skip(start) {
+            if let Err(err) = self.execute(&*statement) {
+                if let TullyError::Yield(_) = err {
+                    yielded = Some(index);
+                }
+                result = Err(err);
+                break;
+            }
         }
-        if new_block {
-            self.globals.delete_recent();
+        let scope = if new_block {
+            self.globals.delete_recent()
+        } else {
+            None
+        };
+        if let Some(index) = yielded {
+            self.frames.push(Resume::Block(index, scope));
+        }
+        result
+    }
+
+    // A const global can not be declared again, nor can a const be
+    // declared over another global. Locals are checked by the resolver,
+    // globals may come from an earlier run of the same module.
+    fn check_redeclaration(
+        &self,
+        name: &Token,
+        slot: Option<usize>,
+        constant: bool,
+    ) -> Result<(), TullyError> {
+        if slot.is_some() {
+            return Ok(());
+        }
+        let module = self.globals.module();
+        let name_str = &name.lexeme;
+        if module.is_constant(name_str) || (constant && module.contains(name_str)) {
+            return Err(Evaluator::error(
+                name,
+                &format!("Cannot redeclare const {}", name_str),
+            ));
         }
         Ok(())
     }
 
-    pub fn error(token: &Token, message: &str) -> TullyError {
-        TullyError::runtime_error_message(token, message)
+    fn import(&mut self, keyword: &Token, path: &str) -> Result<Rc<TullyModule>, TullyError> {
+        let from = self.globals.module().path.clone();
+        let name = self.loader.resolve(from.as_deref(), path);
+        if let Some(module) = self.modules.get(&name) {
+            return Ok(Rc::clone(module));
+        }
+        if let Some(start) = self.importing.iter().position(|module| *module == name) {
+            let mut cycle = self.importing[start..].to_vec();
+            cycle.push(name);
+            return Err(Evaluator::error(
+                keyword,
+                &format!("Import cycle: {}", cycle.join(" -> ")),
+            ));
+        }
+        let source = match self.loader.load(&name) {
+            Ok(source) => source,
+            Err(message) => {
+                return Err(Evaluator::error(
+                    keyword,
+                    &format!("Cannot import {}: {}", name, message),
+                ))
+            }
+        };
+        // the module runs at its own top level, with its own globals
+        let module = Rc::new(TullyModule::new(Some(name.clone())));
+        let previous_module = self.globals.replace_module(Rc::clone(&module));
+        let previous_scope = self.globals.replace(None);
+        self.importing.push(name.clone());
+        let result = self.eval(&source);
+        self.importing.pop();
+        self.globals.replace(previous_scope);
+        self.globals.replace_module(previous_module);
+        result?;
+        self.modules.insert(name, Rc::clone(&module));
+        Ok(module)
     }
-}
 
-impl Visitor<Result<Rc<Value>, TullyError>> for Evaluator {
-    fn visit_binary_operation(&mut self, expr: &Binary) -> Result<Rc<Value>, TullyError> {
-        let left = self.accept(&*expr.left)?;
-        let right = self.accept(&*expr.right)?;
-        let operation = &expr.operator;
-        match operation.tt {
-            TokenType::Plus => match left.borrow() {
-                Value::String(value) => match right.borrow() {
-                    Value::String(value2) => Ok(Rc::new(Value::String(value.to_owned() + value2))),
-                    Value::Float(value2) => {
-                        Ok(Rc::new(Value::String(format!("{}{}", value, value2))))
+    // the value of the arm's body, or `None` when none of its patterns
+    // match or its guard fails
+    fn match_arm(
+        &mut self,
+        arm: &MatchArm,
+        subject: &Rc<Value>,
+    ) -> Result<Option<Rc<Value>>, TullyError> {
+        for pattern in &arm.patterns {
+            if !self.match_pattern(pattern, subject)? {
+                continue;
+            }
+            if let Some(guard) = &arm.guard {
+                if !Evaluator::is_trusty(self.accept(guard)?.borrow()) {
+                    continue;
+                }
+            }
+            return Ok(Some(self.accept(&arm.body)?));
+        }
+        Ok(None)
+    }
+
+    // binds the names in `pattern` as it goes, a pattern that fails half
+    // way may leave some of them bound
+    fn match_pattern(&mut self, pattern: &Pattern, value: &Rc<Value>) -> Result<bool, TullyError> {
+        match pattern {
+            Pattern::Wildcard => Ok(true),
+            Pattern::Literal(literal) => Ok(self.accept(literal)?.equals(value)),
+            Pattern::Binding(name, slot) => {
+                self.globals
+                    .define_at(slot.get(), &name.lexeme, Rc::clone(value));
+                Ok(true)
+            }
+            Pattern::List(patterns) => {
+                let elements = match &**value {
+                    Value::List(list) => {
+                        let list: &RefCell<Vec<Rc<Value>>> = list.borrow();
+                        list.borrow().clone()
                     }
-                    _ => Err(Evaluator::error(
-                        operation,
-                        "Operators must be  strings or numbers for '+' ",
-                    )),
-                },
-                Value::Float(value) => match right.borrow() {
-                    Value::String(value2) => {
-                        return Ok(Rc::new(Value::String(format!("{}{}", value, value2))));
+                    _ => return Ok(false),
+                };
+                if elements.len() != patterns.len() {
+                    return Ok(false);
+                }
+                for (pattern, element) in patterns.iter().zip(elements.iter()) {
+                    if !self.match_pattern(pattern, element)? {
+                        return Ok(false);
                     }
-                    Value::Float(value2) => Ok(Rc::new(Value::Float(value + value2))),
-                    _ => Err(Evaluator::error(
-                        operation,
-                        "Operators must be  strings or numbers for '+' ",
-                    )),
-                },
+                }
+                Ok(true)
+            }
+            Pattern::Map(entries) => {
+                let map: &RefCell<TullyMap> = match &**value {
+                    Value::Map(map) => map.borrow(),
+                    _ => return Ok(false),
+                };
+                for (key, pattern) in entries {
+                    let key = self.accept(key)?;
+                    let entry = key.to_key().and_then(|key| map.borrow().get(&key));
+                    match entry {
+                        Some(entry) if self.match_pattern(pattern, &entry)? => (),
+                        _ => return Ok(false),
+                    }
+                }
+                Ok(true)
+            }
+        }
+    }
+
+    fn execute_catch(&mut self, catch: &Catch, error: Rc<Value>) -> Result<(), TullyError> {
+        self.globals.new_env();
+        if let Some(name) = &catch.name {
+            self.globals
+                .define_at(catch.slot.get(), &name.lexeme, error);
+        }
+        self.execute_catch_body(catch)
+    }
+
+    // runs the statements of the catch block in the scope of the caught
+    // error, which is the current scope
+    fn execute_catch_body(&mut self, catch: &Catch) -> Result<(), TullyError> {
+        let result = self.execute_block(&catch.body.statements, false);
+        let scope = self.globals.delete_recent();
+        if let (Err(TullyError::Yield(_)), Some(scope)) = (&result, scope) {
+            self.frames.push(Resume::Try(TryStage::Catch(scope)));
+        }
+        result
+    }
+
+    // the binary operators that work on two values, shared by binary
+    // expressions and compound assignments
+    fn operate(operation: &Token, left: &Value, right: &Value) -> Result<Rc<Value>, TullyError> {
+        match operation.tt {
+            TokenType::Plus => match (left, right) {
+                (Value::String(value), Value::String(value2)) => {
+                    Ok(Rc::new(Value::String(value.to_owned() + value2)))
+                }
+                (Value::String(value), value2) if value2.is_number() => Ok(Rc::new(Value::String(
+                    format!("{}{}", value, value2.to_string()),
+                ))),
+                (value, Value::String(value2)) if value.is_number() => Ok(Rc::new(Value::String(
+                    format!("{}{}", value.to_string(), value2),
+                ))),
+                (value, value2) if value.is_number() && value2.is_number() => {
+                    Ok(Rc::new(arithmetic::arithmetic(operation, value, value2)?))
+                }
+                // interpolation lowers to `+`, its operator keeps the `${`
+                _ if operation.lexeme == "${" => Err(Evaluator::error(
+                    operation,
+                    "Interpolated values must be strings or numbers",
+                )),
                 _ => Err(Evaluator::error(
                     operation,
                     "Operators must be  strings or numbers for '+' ",
                 )),
             },
-            TokenType::Minus => {
-                let (left_value, right_value) =
-                    Evaluator::check_numbers(left.borrow(), right.borrow())?;
-                Ok(Rc::new(Value::Float(left_value - right_value)))
-            }
-            TokenType::Slash => {
-                let (left_value, right_value) =
-                    Evaluator::check_numbers(left.borrow(), right.borrow())?;
-                Ok(Rc::new(Value::Float(left_value / right_value)))
-            }
-            TokenType::Star => {
-                let (left_value, right_value) =
-                    Evaluator::check_numbers(left.borrow(), right.borrow())?;
-                Ok(Rc::new(Value::Float(left_value * right_value)))
-            }
-            TokenType::Greater => {
-                let (left_value, right_value) =
-                    Evaluator::check_numbers(left.borrow(), right.borrow())?;
-                Ok(Rc::new(Value::Boolean(left_value > right_value)))
-            }
-            TokenType::GreaterEqual => {
-                let (left_value, right_value) =
-                    Evaluator::check_numbers(left.borrow(), right.borrow())?;
-                Ok(Rc::new(Value::Boolean(left_value >= right_value)))
-            }
-            TokenType::Lesser => {
-                let (left_value, right_value) =
-                    Evaluator::check_numbers(left.borrow(), right.borrow())?;
-                Ok(Rc::new(Value::Boolean(left_value < right_value)))
-            }
-            TokenType::LesserEqual => {
-                let (left_value, right_value) =
-                    Evaluator::check_numbers(left.borrow(), right.borrow())?;
-                Ok(Rc::new(Value::Boolean(left_value <= right_value)))
-            }
-            TokenType::EqualEqual => Ok(Rc::new(Value::Boolean(left.equals(&right)))),
-            TokenType::BangEqual => Ok(Rc::new(Value::Boolean(!left.equals(&right)))),
+            TokenType::Minus
+            | TokenType::Slash
+            | TokenType::TildeSlash
+            | TokenType::Star
+            | TokenType::Percentage
+            | TokenType::StarStar => Ok(Rc::new(arithmetic::arithmetic(operation, left, right)?)),
+            TokenType::Ampersand
+            | TokenType::Pipe
+            | TokenType::Caret
+            | TokenType::LessLess
+            | TokenType::GreaterGreater => {
+                Ok(Rc::new(arithmetic::bitwise(operation, left, right)?))
+            }
+            TokenType::Greater
+            | TokenType::GreaterEqual
+            | TokenType::Lesser
+            | TokenType::LesserEqual => Ok(Rc::new(Value::Boolean(arithmetic::compare(
+                operation, left, right,
+            )?))),
+            TokenType::EqualEqual => Ok(Rc::new(Value::Boolean(left.equals(right)))),
+            TokenType::BangEqual => Ok(Rc::new(Value::Boolean(!left.equals(right)))),
             _ => {
                 // Not reachable
                 return Err(Evaluator::error(operation, "Unsupported binary operation"));
@@ -201,9 +436,126 @@ impl Visitor<Result<Rc<Value>, TullyError>> for Evaluator {
         }
     }
 
+    fn get_property(name: &Token, object: &Rc<Value>) -> Result<Rc<Value>, TullyError> {
+        match &**object {
+            Value::Instance(instance) => {
+                let instance: &RefCell<TullyInstance> = instance.borrow();
+                match instance.borrow().get(&name.lexeme, object) {
+                    Some(value) => Ok(value),
+                    None => Err(Evaluator::error(
+                        name,
+                        &format!("Undefined property {}", name.lexeme),
+                    )),
+                }
+            }
+            Value::Module(module) => match module.get(&name.lexeme) {
+                Some(value) => Ok(value),
+                None => Err(Evaluator::error(
+                    name,
+                    &format!("Module {} does not export {}", module.name(), name.lexeme),
+                )),
+            },
+            Value::Error(error) => match error.get(&name.lexeme) {
+                Some(value) => Ok(value),
+                None => Err(Evaluator::error(
+                    name,
+                    &format!("Undefined property {}", name.lexeme),
+                )),
+            },
+            Value::Iterator(iterator) if name.lexeme == "next" => Ok(Rc::new(Value::Function(
+                TullyFunction::NativeFunction(Rc::new(NextMethod::new(Rc::clone(iterator)))),
+            ))),
+            _ => Err(Evaluator::error(name, "Only instances have properties")),
+        }
+    }
+
+    fn set_property(
+        name: &Token,
+        object: &Rc<Value>,
+        value: Rc<Value>,
+    ) -> Result<Rc<Value>, TullyError> {
+        match &**object {
+            Value::Instance(instance) => {
+                instance.borrow_mut().set(&name.lexeme, Rc::clone(&value));
+                Ok(value)
+            }
+            _ => Err(Evaluator::error(name, "Only instances have fields")),
+        }
+    }
+
+    fn get_index(
+        &self,
+        bracket: &Token,
+        object: &Rc<Value>,
+        index: &Rc<Value>,
+    ) -> Result<Rc<Value>, TullyError> {
+        match &**object {
+            Value::List(list) => {
+                let list: &RefCell<Vec<Rc<Value>>> = list.borrow();
+                let list = list.borrow();
+                let index = Evaluator::list_index(bracket, index.borrow(), list.len())?;
+                Ok(Rc::clone(&list[index]))
+            }
+            Value::Map(map) => {
+                let key = Evaluator::map_key(bracket, index.borrow())?;
+                let map: &RefCell<TullyMap> = map.borrow();
+                match map.borrow().get(&key) {
+                    Some(value) => Ok(value),
+                    None => Ok(Rc::clone(&self.constants.nil)),
+                }
+            }
+            _ => Err(Evaluator::error(
+                bracket,
+                "Only lists and maps can be indexed",
+            )),
+        }
+    }
+
+    fn set_index(
+        bracket: &Token,
+        object: &Rc<Value>,
+        index: Rc<Value>,
+        value: Rc<Value>,
+    ) -> Result<Rc<Value>, TullyError> {
+        match &**object {
+            Value::List(list) => {
+                let mut list = list.borrow_mut();
+                let index = Evaluator::list_index(bracket, index.borrow(), list.len())?;
+                list[index] = Rc::clone(&value);
+                Ok(value)
+            }
+            Value::Map(map) => {
+                let key = Evaluator::map_key(bracket, index.borrow())?;
+                map.borrow_mut().insert(key, index, Rc::clone(&value));
+                Ok(value)
+            }
+            _ => Err(Evaluator::error(
+                bracket,
+                "Only lists and maps can be indexed",
+            )),
+        }
+    }
+
+    pub fn error(token: &Token, message: &str) -> TullyError {
+        TullyError::runtime_error_message(token, message)
+    }
+}
+
+impl Visitor<Result<Rc<Value>, TullyError>> for Evaluator {
+    fn visit_binary_operation(&mut self, expr: &Binary) -> Result<Rc<Value>, TullyError> {
+        let left = self.accept(&*expr.left)?;
+        let right = self.accept(&*expr.right)?;
+        Evaluator::operate(&expr.operator, &left, &right)
+    }
+
     fn visit_literal(&mut self, expr: &Literal) -> Result<Rc<Value>, TullyError> {
         match &expr.value {
+            LiteralValue::Int(value) => Ok(Rc::new(Value::Int(*value))),
             LiteralValue::Float(value) => Ok(Rc::new(Value::Float(value.clone()))),
+            #[cfg(feature = "bignum")]
+            LiteralValue::BigInt(value) => Ok(Rc::new(Value::BigInt(value.clone()))),
+            #[cfg(feature = "bignum")]
+            LiteralValue::Decimal(value) => Ok(Rc::new(Value::Decimal(value.clone()))),
             LiteralValue::String(value) => Ok(Rc::new(Value::String(value.clone()))),
             LiteralValue::Boolean(value) => Ok(Rc::new(Value::Boolean(value.clone()))),
             LiteralValue::Nil => return Ok(Rc::clone(&self.constants.nil)),
@@ -215,13 +567,19 @@ impl Visitor<Result<Rc<Value>, TullyError>> for Evaluator {
 
         match expr.operator.tt {
             TokenType::Plus => {
-                let value = Evaluator::check_number(value.borrow())?;
-                Ok(Rc::new(Value::Float(value)))
-            }
-            TokenType::Minus => {
-                let value = Evaluator::check_number(value.borrow())?;
-                Ok(Rc::new(Value::Float(-value)))
+                if !value.is_number() {
+                    return Err(Evaluator::error(
+                        &expr.operator,
+                        "Expecting number in unary operation",
+                    ));
+                }
+                Ok(value)
             }
+            TokenType::Minus => Ok(Rc::new(arithmetic::negate(&expr.operator, value.borrow())?)),
+            TokenType::Tilde => Ok(Rc::new(arithmetic::complement(
+                &expr.operator,
+                value.borrow(),
+            )?)),
             TokenType::Bang => {
                 let value = Evaluator::is_trusty(value.borrow());
                 Ok(Rc::new(Value::Boolean(!value)))
@@ -247,12 +605,14 @@ impl Visitor<Result<Rc<Value>, TullyError>> for Evaluator {
 
     fn visit_print(&mut self, expr: &Print) -> Result<Rc<Value>, TullyError> {
         let value = self.accept(&*expr.expression)?;
-        println!("{}", value.to_string());
+        if let Err(e) = writeln!(self.output, "{}", value.to_string()) {
+            return Err(TullyError::RunTimeMessage(e.to_string()));
+        }
         return Ok(Rc::clone(&self.constants.nil));
     }
 
     fn visit_variable(&mut self, expr: &Variable) -> Result<Rc<Value>, TullyError> {
-        match self.globals.get(&expr.name.lexeme) {
+        match self.globals.get_at(expr.slot.get(), &expr.name.lexeme) {
             Some(value) => Ok(value),
             None => Err(Evaluator::error(
                 &expr.name,
@@ -262,21 +622,38 @@ impl Visitor<Result<Rc<Value>, TullyError>> for Evaluator {
     }
 
     fn visit_var(&mut self, expr: &Var) -> Result<Rc<Value>, TullyError> {
+        self.check_redeclaration(&expr.name, expr.slot.get(), expr.constant)?;
+        if expr.slot.get().is_none() {
+            let module = self.globals.module();
+            let name = &expr.name.lexeme;
+            if expr.constant {
+                let value = match &expr.initializer {
+                    Some(value) => self.accept(&*value)?,
+                    None => Rc::clone(&self.constants.nil),
+                };
+                module.define_constant(name, value);
+                return Ok(Rc::clone(&self.constants.nil));
+            }
+        }
         match &expr.initializer {
             Some(value) => {
                 let value = self.accept(&*value)?;
-                self.globals.define(&expr.name, value);
+                self.globals
+                    .define_at(expr.slot.get(), &expr.name.lexeme, value);
             }
-            None => self
-                .globals
-                .define(&expr.name, Rc::clone(&self.constants.nil)),
+            None => self.globals.define_at(
+                expr.slot.get(),
+                &expr.name.lexeme,
+                Rc::clone(&self.constants.nil),
+            ),
         }
         Ok(Rc::clone(&self.constants.nil))
     }
 
     fn visit_assign(&mut self, expr: &Assign) -> Result<Rc<Value>, TullyError> {
         let value = self.accept(&*expr.initializer)?;
-        self.globals.assign(&expr.name, Rc::clone(&value))?;
+        self.globals
+            .assign_at(expr.slot.get(), &expr.name, Rc::clone(&value))?;
         Ok(Rc::clone(&value))
     }
 
@@ -286,23 +663,44 @@ impl Visitor<Result<Rc<Value>, TullyError>> for Evaluator {
     }
 
     fn visit_if_statement(&mut self, expr: &IfStatement) -> Result<Rc<Value>, TullyError> {
-        let condition = self.accept(&*expr.condition)?;
-        if Evaluator::is_trusty(condition.borrow()) {
-            self.accept(&*expr.then_branch)?;
-            return Ok(Rc::clone(&self.constants.nil));
-        }
-        match &expr.else_branch {
-            Some(value) => {
-                self.accept(&*value)?;
-            }
-            None => (),
+        let then = match self.frames.pop() {
+            Some(Resume::If(then)) => then,
+            Some(_) => unreachable!("an if resumes from an if frame"),
+            None => Evaluator::is_trusty(self.accept(&*expr.condition)?.borrow()),
         };
+        let result = match (then, &expr.else_branch) {
+            (true, _) => self.accept(&*expr.then_branch),
+            (false, Some(value)) => self.accept(&*value),
+            (false, None) => Ok(Rc::clone(&self.constants.nil)),
+        };
+        if let Err(TullyError::Yield(_)) = result {
+            self.frames.push(Resume::If(then));
+        }
+        result?;
         Ok(Rc::clone(&self.constants.nil))
     }
 
     fn visit_while_statement(&mut self, expr: &WhileStatement) -> Result<Rc<Value>, TullyError> {
-        while Evaluator::is_trusty(self.accept(&*expr.condition)?.borrow()) {
-            self.accept(&*expr.body)?;
+        // a resumed loop goes back into its body without checking again
+        let mut resumed = match self.frames.pop() {
+            Some(Resume::While) => true,
+            Some(_) => unreachable!("a while resumes from a while frame"),
+            None => false,
+        };
+        while resumed || Evaluator::is_trusty(self.accept(&*expr.condition)?.borrow()) {
+            resumed = false;
+            match self.accept(&*expr.body) {
+                Err(TullyError::Break) => break,
+                Ok(_) | Err(TullyError::Continue) => (),
+                Err(err @ TullyError::Yield(_)) => {
+                    self.frames.push(Resume::While);
+                    return Err(err);
+                }
+                Err(err) => return Err(err),
+            }
+            if let Some(increment) = &expr.increment {
+                self.accept(&*increment)?;
+            }
         }
         Ok(Rc::clone(&self.constants.nil))
     }
@@ -311,7 +709,21 @@ impl Visitor<Result<Rc<Value>, TullyError>> for Evaluator {
         let callee = self.accept(&*expr.callee)?;
         let mut arguments = Vec::new();
         for arg in &expr.arguments {
-            arguments.push(self.accept(&*arg)?);
+            match &**arg {
+                ExprType::Spread(spread) => {
+                    let value = self.accept(&*spread.value)?;
+                    match &*value {
+                        Value::List(list) => {
+                            let list: &RefCell<Vec<Rc<Value>>> = list.borrow();
+                            arguments.extend(list.borrow().iter().map(Rc::clone));
+                        }
+                        _ => {
+                            return Err(Evaluator::error(&spread.ellipsis, "Can only spread lists"))
+                        }
+                    }
+                }
+                arg => arguments.push(self.accept(arg)?),
+            }
         }
         match &*callee {
             Value::Function(function) => {
@@ -320,44 +732,40 @@ impl Visitor<Result<Rc<Value>, TullyError>> for Evaluator {
                     TullyFunction::NFunction(nf) => {
                         //                        println!("Calling {:?}", nf.deref().borrow().to_string());
                         let nf: &RefCell<TullyCallable> = nf.borrow();
-                        if nf.borrow().arity() != arguments.len() {
-                            return Err(Evaluator::error(
-                                &expr.paren,
-                                &format!(
-                                    "Expected {} args but got {}",
-                                    nf.borrow().arity(),
-                                    arguments.len()
-                                ),
-                            ));
-                        }
+                        Evaluator::check_arity(&expr.paren, &*nf.borrow(), arguments.len())?;
                         nf.borrow().call(self, arguments)
                     }
                     TullyFunction::NativeFunction(nf) => {
-                        if nf.arity() != arguments.len() {
-                            return Err(Evaluator::error(
-                                &expr.paren,
-                                &format!(
-                                    "Expected {} args but got {}",
-                                    nf.arity(),
-                                    arguments.len()
-                                ),
-                            ));
-                        }
-                        nf.call(self, arguments)
+                        Evaluator::check_arity(&expr.paren, &**nf, arguments.len())?;
+                        // natives don't know where they are called from
+                        nf.call(self, arguments).map_err(|err| match err {
+                            TullyError::RunTimeMessage(message) => {
+                                Evaluator::error(&expr.paren, &message)
+                            }
+                            err => err,
+                        })
                     }
                 }
             }
+            Value::Class(class) => {
+                Evaluator::check_arity(&expr.paren, class, arguments.len())?;
+                class.call(self, arguments)
+            }
             _ => Err(Evaluator::error(&expr.paren, " Not a callable")),
         }
     }
 
-    fn visit_function(&mut self, expr: &Function) -> Result<Rc<Value>, TullyError> {
+    fn visit_function(&mut self, expr: &Rc<Function>) -> Result<Rc<Value>, TullyError> {
+        self.check_redeclaration(&expr.name, expr.slot.get(), false)?;
         let name = &expr.name.lexeme;
-        let function = TullyCallable {
-            declaration: expr.clone(),
-            closure: None,
-        };
-        self.globals.define(
+        let function = TullyCallable::new(
+            Rc::clone(expr),
+            self.globals.capture(),
+            self.globals.module(),
+            false,
+        );
+        self.globals.define_at(
+            expr.slot.get(),
             name,
             Rc::new(Value::Function(TullyFunction::NFunction(Rc::new(
                 RefCell::new(function),
@@ -375,4 +783,443 @@ impl Visitor<Result<Rc<Value>, TullyError>> for Evaluator {
             None => Err(TullyError::Return(Rc::clone(&self.constants.nil))),
         }
     }
+
+    fn visit_logical(&mut self, expr: &Logical) -> Result<Rc<Value>, TullyError> {
+        let left = self.accept(&*expr.left)?;
+        match expr.operator.tt {
+            TokenType::Or => {
+                if Evaluator::is_trusty(left.borrow()) {
+                    return Ok(left);
+                }
+            }
+            TokenType::QuestionQuestion => {
+                if !matches!(*left, Value::Nil) {
+                    return Ok(left);
+                }
+            }
+            _ => {
+                if !Evaluator::is_trusty(left.borrow()) {
+                    return Ok(left);
+                }
+            }
+        }
+        self.accept(&*expr.right)
+    }
+
+    fn visit_class(&mut self, expr: &Class) -> Result<Rc<Value>, TullyError> {
+        self.check_redeclaration(&expr.name, expr.slot.get(), false)?;
+        let mut superclass = None;
+        if let Some(variable) = &expr.superclass {
+            match &*self.visit_variable(variable)? {
+                Value::Class(class) => superclass = Some(Rc::clone(class)),
+                _ => {
+                    return Err(Evaluator::error(
+                        &variable.name,
+                        "Superclass must be a class",
+                    ))
+                }
+            }
+        }
+        let mut closure = self.globals.capture();
+        if let Some(superclass) = &superclass {
+            let superclass = Rc::new(Value::Class(Rc::clone(superclass)));
+            let scope = Scope::new(vec![superclass], closure);
+            closure = Some(Rc::new(RefCell::new(scope)));
+        }
+        let mut methods = HashMap::new();
+        for method in &expr.methods {
+            let is_initializer = method.name.lexeme == "init";
+            let function = TullyCallable::new(
+                Rc::clone(method),
+                closure.clone(),
+                self.globals.module(),
+                is_initializer,
+            );
+            methods.insert(method.name.lexeme.clone(), function);
+        }
+        let class = TullyClass {
+            name: expr.name.lexeme.clone(),
+            superclass,
+            methods,
+        };
+        self.globals.define_at(
+            expr.slot.get(),
+            &expr.name.lexeme,
+            Rc::new(Value::Class(Rc::new(class))),
+        );
+        Ok(Rc::clone(&self.constants.nil))
+    }
+
+    fn visit_get(&mut self, expr: &Get) -> Result<Rc<Value>, TullyError> {
+        let object = self.accept(&*expr.object)?;
+        if expr.optional && matches!(*object, Value::Nil) {
+            return Err(TullyError::ShortCircuit);
+        }
+        Evaluator::get_property(&expr.name, &object)
+    }
+
+    fn visit_set(&mut self, expr: &Set) -> Result<Rc<Value>, TullyError> {
+        let object = self.accept(&*expr.object)?;
+        let value = self.accept(&*expr.value)?;
+        Evaluator::set_property(&expr.name, &object, value)
+    }
+
+    fn visit_this(&mut self, expr: &This) -> Result<Rc<Value>, TullyError> {
+        match self.globals.get_at(expr.slot.get(), &expr.keyword.lexeme) {
+            Some(value) => Ok(value),
+            None => Err(Evaluator::error(
+                &expr.keyword,
+                "Cannot use 'this' outside of a class",
+            )),
+        }
+    }
+
+    fn visit_super(&mut self, expr: &Super) -> Result<Rc<Value>, TullyError> {
+        let slot = expr.slot.get();
+        let superclass = self.globals.get_at(slot, &expr.keyword.lexeme);
+        let this = slot.and_then(|slot| {
+            let this = Slot {
+                depth: slot.depth - 1,
+                index: 0,
+            };
+            self.globals.get_at(Some(this), &String::from("this"))
+        });
+        let (superclass, this) = match (superclass, this) {
+            (Some(superclass), Some(this)) => (superclass, this),
+            _ => {
+                return Err(Evaluator::error(
+                    &expr.keyword,
+                    "Cannot use 'super' outside of a subclass",
+                ))
+            }
+        };
+        let method = match &*superclass {
+            Value::Class(class) => class.find_method(&expr.method.lexeme),
+            _ => None,
+        };
+        match method {
+            Some(method) => Ok(Rc::new(Value::Function(TullyFunction::NFunction(Rc::new(
+                RefCell::new(method.bind(this)),
+            ))))),
+            None => Err(Evaluator::error(
+                &expr.method,
+                &format!("Undefined property {}", expr.method.lexeme),
+            )),
+        }
+    }
+
+    fn visit_list(&mut self, expr: &List) -> Result<Rc<Value>, TullyError> {
+        let mut elements = Vec::new();
+        for element in &expr.elements {
+            elements.push(self.accept(&*element)?);
+        }
+        Ok(Rc::new(Value::List(Rc::new(RefCell::new(elements)))))
+    }
+
+    fn visit_index(&mut self, expr: &Index) -> Result<Rc<Value>, TullyError> {
+        let object = self.accept(&*expr.object)?;
+        let index = self.accept(&*expr.index)?;
+        self.get_index(&expr.bracket, &object, &index)
+    }
+
+    fn visit_set_index(&mut self, expr: &SetIndex) -> Result<Rc<Value>, TullyError> {
+        let object = self.accept(&*expr.object)?;
+        let index = self.accept(&*expr.index)?;
+        let value = self.accept(&*expr.value)?;
+        Evaluator::set_index(&expr.bracket, &object, index, value)
+    }
+
+    fn visit_map(&mut self, expr: &Map) -> Result<Rc<Value>, TullyError> {
+        let mut map = TullyMap::new();
+        for (key, value) in &expr.entries {
+            let key = self.accept(&*key)?;
+            let value = self.accept(&*value)?;
+            map.insert(Evaluator::map_key(&expr.brace, key.borrow())?, key, value);
+        }
+        Ok(Rc::new(Value::Map(Rc::new(RefCell::new(map)))))
+    }
+
+    fn visit_break(&mut self, _expr: &Break) -> Result<Rc<Value>, TullyError> {
+        Err(TullyError::Break)
+    }
+
+    fn visit_continue(&mut self, _expr: &Continue) -> Result<Rc<Value>, TullyError> {
+        Err(TullyError::Continue)
+    }
+
+    fn visit_lambda(&mut self, expr: &Rc<Function>) -> Result<Rc<Value>, TullyError> {
+        let function = TullyCallable::new(
+            Rc::clone(expr),
+            self.globals.capture(),
+            self.globals.module(),
+            false,
+        );
+        Ok(Rc::new(Value::Function(TullyFunction::NFunction(Rc::new(
+            RefCell::new(function),
+        )))))
+    }
+
+    fn visit_throw(&mut self, expr: &Throw) -> Result<Rc<Value>, TullyError> {
+        let value = self.accept(&*expr.value)?;
+        let line = match &*value {
+            // rethrowing keeps the line the error was first raised at
+            Value::Error(error) => error.line,
+            _ => expr.keyword.line,
+        };
+        Err(TullyError::Throw(value, line))
+    }
+
+    fn visit_try(&mut self, expr: &Try) -> Result<Rc<Value>, TullyError> {
+        // a resumed try continues in the block it yielded in
+        let stage = match self.frames.pop() {
+            Some(Resume::Try(stage)) => stage,
+            Some(_) => unreachable!("a try resumes from a try frame"),
+            None => TryStage::Body,
+        };
+        // returns, breaks and continues unwind through the try untouched,
+        // only thrown values are caught
+        let result = match (stage, &expr.catch) {
+            (TryStage::Body, catch) => {
+                match (self.execute_block(&expr.body.statements, true), catch) {
+                    (Err(err @ TullyError::Yield(_)), _) => {
+                        self.frames.push(Resume::Try(TryStage::Body));
+                        return Err(err);
+                    }
+                    (Err(TullyError::Throw(value, _)), Some(catch)) => {
+                        self.execute_catch(catch, value)
+                    }
+                    (result, _) => result,
+                }
+            }
+            (TryStage::Catch(scope), Some(catch)) => {
+                self.globals.replace(Some(scope));
+                self.execute_catch_body(catch)
+            }
+            (TryStage::Catch(_), None) => unreachable!("only a catch block resumes in a catch"),
+            (TryStage::Finally(result), _) => result,
+        };
+        // a yield in the catch block suspends the try before the finally
+        if let Err(TullyError::Yield(_)) = result {
+            return result.map(|_| Rc::clone(&self.constants.nil));
+        }
+        if let Some(finally) = &expr.finally {
+            // anything the finally block unwinds with replaces the result
+            // of the try and catch blocks
+            match self.execute_block(&finally.statements, true) {
+                Err(err @ TullyError::Yield(_)) => {
+                    self.frames.push(Resume::Try(TryStage::Finally(result)));
+                    return Err(err);
+                }
+                finally => finally?,
+            }
+        }
+        result?;
+        Ok(Rc::clone(&self.constants.nil))
+    }
+
+    fn visit_import(&mut self, expr: &Import) -> Result<Rc<Value>, TullyError> {
+        let path = match &expr.path.literal {
+            Some(LiteralValue::String(path)) => path.clone(),
+            _ => return Err(Evaluator::error(&expr.path, "Module path must be a string")),
+        };
+        let names = expr.alias.iter().chain(expr.names.iter());
+        for (name, slot) in names.zip(expr.slots.iter()) {
+            self.check_redeclaration(name, slot.get(), false)?;
+        }
+        let module = self.import(&expr.keyword, &path)?;
+        if let Some(alias) = &expr.alias {
+            let value = Rc::new(Value::Module(module));
+            self.globals
+                .define_at(expr.slots[0].get(), &alias.lexeme, value);
+            return Ok(Rc::clone(&self.constants.nil));
+        }
+        for (name, slot) in expr.names.iter().zip(expr.slots.iter()) {
+            let value = match module.get(&name.lexeme) {
+                Some(value) => value,
+                None => {
+                    return Err(Evaluator::error(
+                        name,
+                        &format!("Module {} does not export {}", module.name(), name.lexeme),
+                    ))
+                }
+            };
+            self.globals.define_at(slot.get(), &name.lexeme, value);
+        }
+        Ok(Rc::clone(&self.constants.nil))
+    }
+
+    fn visit_match(&mut self, expr: &Match) -> Result<Rc<Value>, TullyError> {
+        let subject = self.accept(&*expr.subject)?;
+        for arm in &expr.arms {
+            self.globals.new_env();
+            let result = self.match_arm(arm, &subject);
+            self.globals.delete_recent();
+            if let Some(value) = result? {
+                return Ok(value);
+            }
+        }
+        Err(Evaluator::error(
+            &expr.keyword,
+            &format!("No match arm matches {}", subject.to_string()),
+        ))
+    }
+
+    fn visit_compound(&mut self, expr: &Compound) -> Result<Rc<Value>, TullyError> {
+        let (old, new) = match &*expr.target {
+            ExprType::Variable(variable) => {
+                let old = self.visit_variable(variable)?;
+                let value = self.accept(&*expr.value)?;
+                let new = Evaluator::operate(&expr.operator, &old, &value)?;
+                self.globals
+                    .assign_at(variable.slot.get(), &variable.name, Rc::clone(&new))?;
+                (old, new)
+            }
+            ExprType::Index(index) => {
+                let object = self.accept(&*index.object)?;
+                let key = self.accept(&*index.index)?;
+                let old = self.get_index(&index.bracket, &object, &key)?;
+                let value = self.accept(&*expr.value)?;
+                let new = Evaluator::operate(&expr.operator, &old, &value)?;
+                (
+                    old,
+                    Evaluator::set_index(&index.bracket, &object, key, new)?,
+                )
+            }
+            ExprType::Get(get) => {
+                let object = self.accept(&*get.object)?;
+                let old = Evaluator::get_property(&get.name, &object)?;
+                let value = self.accept(&*expr.value)?;
+                let new = Evaluator::operate(&expr.operator, &old, &value)?;
+                (old, Evaluator::set_property(&get.name, &object, new)?)
+            }
+            _ => {
+                return Err(Evaluator::error(
+                    &expr.operator,
+                    "Invalid assignment target",
+                ))
+            }
+        };
+        if expr.postfix {
+            return Ok(old);
+        }
+        Ok(new)
+    }
+
+    fn visit_conditional(&mut self, expr: &Conditional) -> Result<Rc<Value>, TullyError> {
+        let condition = self.accept(&*expr.condition)?;
+        if Evaluator::is_trusty(condition.borrow()) {
+            return self.accept(&*expr.then_branch);
+        }
+        self.accept(&*expr.else_branch)
+    }
+
+    // spreads are taken apart by `visit_call`, the only place they parse
+    fn visit_spread(&mut self, expr: &Spread) -> Result<Rc<Value>, TullyError> {
+        self.accept(&*expr.value)
+    }
+
+    fn visit_range(&mut self, expr: &Range) -> Result<Rc<Value>, TullyError> {
+        let start = self.accept(&*expr.start)?;
+        let end = self.accept(&*expr.end)?;
+        let step = match &expr.step {
+            Some(step) => self.accept(&*step)?,
+            None => Rc::new(Value::Int(1)),
+        };
+        match (&*start, &*end, &*step) {
+            (_, _, Value::Int(0)) => Err(Evaluator::error(
+                &expr.operator,
+                "Range step cannot be zero",
+            )),
+            (Value::Int(start), Value::Int(end), Value::Int(step)) => {
+                Ok(Rc::new(Value::Range(TullyRange {
+                    start: *start,
+                    end: *end,
+                    step: *step,
+                    inclusive: matches!(expr.operator.tt, TokenType::DotDotEqual),
+                })))
+            }
+            _ => Err(Evaluator::error(
+                &expr.operator,
+                "Range bounds and step must be integers",
+            )),
+        }
+    }
+
+    fn visit_for_in(&mut self, expr: &ForIn) -> Result<Rc<Value>, TullyError> {
+        let (iterator, mut resumed) = match self.frames.pop() {
+            Some(Resume::ForIn(iterator, scope)) => (iterator, Some(scope)),
+            Some(_) => unreachable!("a for-in resumes from a for-in frame"),
+            None => {
+                let iterable = self.accept(&*expr.iterable)?;
+                match iterator(&iterable) {
+                    Some(iterator) => (iterator, None),
+                    None => {
+                        return Err(Evaluator::error(
+                            &expr.keyword,
+                            "Can only iterate over ranges, strings, lists, maps and iterators",
+                        ))
+                    }
+                }
+            }
+        };
+        loop {
+            match resumed.take() {
+                Some(scope) => {
+                    self.globals.replace(Some(scope));
+                }
+                None => {
+                    // native iterators don't know where they are used from
+                    let next = next(&iterator, self).map_err(|err| match err {
+                        TullyError::RunTimeMessage(message) => {
+                            Evaluator::error(&expr.keyword, &message)
+                        }
+                        err => err,
+                    });
+                    let item = match next? {
+                        Some(item) => item,
+                        None => break,
+                    };
+                    // the resolver gave the loop variable the first slot of
+                    // the iteration's scope
+                    self.globals.new_env();
+                    self.globals.define_at(Some(0), &expr.name.lexeme, item);
+                }
+            }
+            let result = self.accept(&*expr.body);
+            let scope = self.globals.delete_recent();
+            match result {
+                Err(TullyError::Break) => break,
+                Ok(_) | Err(TullyError::Continue) => (),
+                Err(err @ TullyError::Yield(_)) => {
+                    if let Some(scope) = scope {
+                        self.frames.push(Resume::ForIn(iterator, scope));
+                    }
+                    return Err(err);
+                }
+                Err(err) => return Err(err),
+            }
+        }
+        Ok(Rc::clone(&self.constants.nil))
+    }
+
+    fn visit_yield(&mut self, expr: &Yield) -> Result<Rc<Value>, TullyError> {
+        // resuming at this yield continues after it
+        if let Some(frame) = self.frames.pop() {
+            debug_assert!(matches!(frame, Resume::Yield));
+            return Ok(Rc::clone(&self.constants.nil));
+        }
+        let value = match &expr.value {
+            Some(value) => self.accept(&*value)?,
+            None => Rc::clone(&self.constants.nil),
+        };
+        self.frames.push(Resume::Yield);
+        Err(TullyError::Yield(value))
+    }
+
+    fn visit_optional_chain(&mut self, expr: &OptionalChain) -> Result<Rc<Value>, TullyError> {
+        match self.accept(&*expr.expression) {
+            Err(TullyError::ShortCircuit) => Ok(Rc::clone(&self.constants.nil)),
+            result => result,
+        }
+    }
 }
@@ -0,0 +1,80 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use crate::environment::Scope;
+use crate::error::TullyError;
+use crate::evaluator::{Evaluator, TullyCallable, TullyIterator};
+use crate::value::Value;
+
+// Where a statement was when a `yield` inside it suspended the generator.
+// A yield unwinds as `TullyError::Yield` and every statement it passes
+// pushes its frame, innermost first; resuming runs the body again and
+// every statement on the way back down pops its frame and continues from
+// there instead of starting over.
+pub enum Resume {
+    // the statement to continue at and the scope of the block, `None` for
+    // the body of the generator which runs in the call's scope
+    Block(usize, Option<Rc<RefCell<Scope>>>),
+    // whether the then branch was taken
+    If(bool),
+    While,
+    ForIn(Rc<RefCell<dyn TullyIterator>>, Rc<RefCell<Scope>>),
+    Try(TryStage),
+    Yield,
+}
+
+// The part of a try statement a yield suspended
+pub enum TryStage {
+    Body,
+    // the scope holding the caught error
+    Catch(Rc<RefCell<Scope>>),
+    // what the body and the catch block ended with, which the try ends
+    // with too unless the finally block unwinds with something else
+    Finally(Result<(), TullyError>),
+}
+
+// A call to a `fun*` function. The body runs on the first `next` up to
+// its first `yield`, every later `next` continues it to the next one.
+pub struct Generator {
+    function: TullyCallable,
+    // the scope holding the arguments of the call
+    scope: Rc<RefCell<Scope>>,
+    // where the body is suspended, `None` once it has finished
+    frames: Option<Vec<Resume>>,
+}
+
+impl Generator {
+    pub fn new(function: TullyCallable, scope: Rc<RefCell<Scope>>) -> Generator {
+        Generator {
+            function,
+            scope,
+            frames: Some(Vec::new()),
+        }
+    }
+}
+
+impl TullyIterator for Generator {
+    fn next(&mut self, evaluator: &mut Evaluator) -> Result<Option<Rc<Value>>, TullyError> {
+        let frames = match self.frames.take() {
+            Some(frames) => frames,
+            None => return Ok(None),
+        };
+        let module = evaluator
+            .globals
+            .replace_module(Rc::clone(&self.function.module));
+        let previous = evaluator.globals.replace(Some(Rc::clone(&self.scope)));
+        let outer = std::mem::replace(&mut evaluator.frames, frames);
+        let result = evaluator.execute_block(&self.function.declaration.body.statements, false);
+        let frames = std::mem::replace(&mut evaluator.frames, outer);
+        evaluator.globals.replace(previous);
+        evaluator.globals.replace_module(module);
+        match result {
+            Err(TullyError::Yield(value)) => {
+                self.frames = Some(frames);
+                Ok(Some(value))
+            }
+            Ok(_) | Err(TullyError::Return(_)) => Ok(None),
+            Err(err) => Err(err),
+        }
+    }
+}
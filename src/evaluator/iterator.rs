@@ -0,0 +1,134 @@
+use std::cell::RefCell;
+use std::ops::Deref;
+use std::rc::Rc;
+
+use crate::error::TullyError;
+use crate::evaluator::{Callable, Evaluator};
+use crate::value::{TullyMap, TullyRange, Value};
+
+// What a `for (x in ...)` loop walks. Natives can hand out their own
+// iterables by returning a `Value::Iterator`; `next` returns `None` once
+// the iterator is exhausted.
+pub trait TullyIterator {
+    fn next(&mut self, evaluator: &mut Evaluator) -> Result<Option<Rc<Value>>, TullyError>;
+}
+
+// The iterator over `value`, `None` when it is not iterable. Iterating an
+// iterator value continues where the last loop over it stopped.
+pub fn iterator(value: &Value) -> Option<Rc<RefCell<dyn TullyIterator>>> {
+    let iterator: Rc<RefCell<dyn TullyIterator>> = match value {
+        Value::Range(range) => Rc::new(RefCell::new(RangeIterator {
+            next: Some(range.start),
+            range: *range,
+        })),
+        Value::String(string) => Rc::new(RefCell::new(ValuesIterator::new(
+            string
+                .chars()
+                .map(|c| Rc::new(Value::String(c.to_string())))
+                .collect(),
+        ))),
+        Value::List(list) => Rc::new(RefCell::new(ListIterator {
+            list: Rc::clone(list),
+            index: 0,
+        })),
+        Value::Map(map) => {
+            let map: &RefCell<TullyMap> = map.deref();
+            Rc::new(RefCell::new(ValuesIterator::new(map.borrow().keys())))
+        }
+        Value::Iterator(iterator) => Rc::clone(iterator),
+        _ => return None,
+    };
+    Some(iterator)
+}
+
+// Advances `iterator`, which may be a generator that is already running
+// when its own body iterates it
+pub fn next(
+    iterator: &RefCell<dyn TullyIterator>,
+    evaluator: &mut Evaluator,
+) -> Result<Option<Rc<Value>>, TullyError> {
+    match iterator.try_borrow_mut() {
+        Ok(mut iterator) => iterator.next(evaluator),
+        Err(_) => Err(TullyError::RunTimeMessage(String::from(
+            "Iterator is already running",
+        ))),
+    }
+}
+
+// `iterator.next()`, the next value or nil once the iterator is exhausted
+pub struct NextMethod {
+    iterator: Rc<RefCell<dyn TullyIterator>>,
+}
+
+impl NextMethod {
+    pub fn new(iterator: Rc<RefCell<dyn TullyIterator>>) -> NextMethod {
+        NextMethod { iterator }
+    }
+}
+
+impl Callable for NextMethod {
+    fn arity(&self) -> usize {
+        return 0;
+    }
+
+    fn call(&self, evaluator: &mut Evaluator, _: Vec<Rc<Value>>) -> Result<Rc<Value>, TullyError> {
+        match next(&self.iterator, evaluator)? {
+            Some(value) => Ok(value),
+            None => Ok(Rc::clone(&evaluator.constants.nil)),
+        }
+    }
+
+    fn to_string(&self) -> String {
+        String::from("<native fn next>")
+    }
+}
+
+struct RangeIterator {
+    next: Option<i64>,
+    range: TullyRange,
+}
+
+impl TullyIterator for RangeIterator {
+    fn next(&mut self, _: &mut Evaluator) -> Result<Option<Rc<Value>>, TullyError> {
+        let value = match self.next {
+            Some(value) if self.range.contains(value) => value,
+            _ => return Ok(None),
+        };
+        // stepping past `i64::MAX` ends the range
+        self.next = value.checked_add(self.range.step);
+        Ok(Some(Rc::new(Value::Int(value))))
+    }
+}
+
+// Reads the list as it goes, so elements the loop body assigns ahead of
+// the current index are seen with their new values
+struct ListIterator {
+    list: Rc<RefCell<Vec<Rc<Value>>>>,
+    index: usize,
+}
+
+impl TullyIterator for ListIterator {
+    fn next(&mut self, _: &mut Evaluator) -> Result<Option<Rc<Value>>, TullyError> {
+        let value = self.list.borrow().get(self.index).map(Rc::clone);
+        self.index += 1;
+        Ok(value)
+    }
+}
+
+struct ValuesIterator {
+    values: std::vec::IntoIter<Rc<Value>>,
+}
+
+impl ValuesIterator {
+    fn new(values: Vec<Rc<Value>>) -> ValuesIterator {
+        ValuesIterator {
+            values: values.into_iter(),
+        }
+    }
+}
+
+impl TullyIterator for ValuesIterator {
+    fn next(&mut self, _: &mut Evaluator) -> Result<Option<Rc<Value>>, TullyError> {
+        Ok(self.values.next())
+    }
+}
@@ -1,6 +1,15 @@
 pub use callable::Callable;
 pub use callable::TullyCallable;
+pub use class::TullyClass;
+pub use class::TullyInstance;
 pub use evaluator::Evaluator;
+pub use iterator::TullyIterator;
 
+mod arithmetic;
+#[cfg(feature = "bignum")]
+pub(crate) mod bignum;
 mod callable;
+mod class;
 mod evaluator;
+mod generator;
+mod iterator;
@@ -1,6 +1,10 @@
+#[cfg(feature = "bignum")]
+use num_bigint::BigInt;
+
 use crate::error::TullyError;
 use crate::lexer::token::{Token, TokenType};
 use crate::tully::Tully;
+use crate::value::LiteralValue;
 
 pub struct Lexer {
     expr_chars: Vec<char>,
@@ -8,6 +12,8 @@ pub struct Lexer {
     start: usize,
     current: usize,
     line: usize,
+    // brace depth of every `${` interpolation we are currently inside
+    interpolations: Vec<usize>,
 }
 
 impl Lexer {
@@ -19,6 +25,7 @@ impl Lexer {
             start: 0,
             current: 0,
             line: 1,
+            interpolations: Vec::new(),
         };
     }
 
@@ -54,6 +61,10 @@ impl Lexer {
         return token;
     }
 
+    fn literal_token(&mut self, tt: TokenType, literal: LiteralValue) -> Token {
+        self.token_type(tt).with_literal(literal)
+    }
+
     fn space(&mut self) -> char {
         let mut c = self.eat();
         while c.is_whitespace() {
@@ -68,22 +79,166 @@ impl Lexer {
 
     pub fn next_token(&mut self) -> Result<Token, TullyError> {
         if self.is_at_end() {
+            if !self.interpolations.is_empty() {
+                return Err(self.error("Unterminated string interpolation"));
+            }
             return Ok(Token::end_of_line());
         }
         self.scan_token()
     }
 
-    fn number(&mut self) -> Token {
-        while self.peek(0).is_digit(10) {
-            self.eat();
+    // the first digit has already been eaten
+    fn number(&mut self) -> Result<Token, TullyError> {
+        let first = self.expr_chars[self.start];
+        if first == '0' {
+            let radix = match self.peek(0) {
+                'x' | 'X' => Some(16),
+                'o' | 'O' => Some(8),
+                'b' | 'B' => Some(2),
+                _ => None,
+            };
+            if let Some(radix) = radix {
+                self.eat();
+                return self.radix_number(radix);
+            }
         }
+        let mut digits = first.to_string();
+        self.digits(&mut digits, 10)?;
+        let mut fraction = false;
         if self.peek(0) == '.' && self.peek(1).is_digit(10) {
+            fraction = true;
+            digits.push(self.eat());
+            self.digits(&mut digits, 10)?;
+        }
+        let mut exponent = false;
+        if self.peek(0) == 'e' || self.peek(0) == 'E' {
+            exponent = true;
+            digits.push(self.eat());
+            if self.peek(0) == '+' || self.peek(0) == '-' {
+                digits.push(self.eat());
+            }
+            if !self.peek(0).is_digit(10) {
+                return Err(self.error(&format!("Expected digits after {}", self.lexeme())));
+            }
+            self.digits(&mut digits, 10)?;
+        }
+        let suffix = self.peek(0);
+        if (suffix == 'n' || suffix == 'd')
+            && !(self.peek(1).is_alphanumeric() || self.peek(1) == '_')
+        {
+            self.eat();
+            return self.big_number(digits, 10, suffix, fraction || exponent);
+        }
+        self.end_of_number()?;
+        if fraction || exponent {
+            return match digits.parse::<f64>() {
+                Ok(value) if value.is_finite() => {
+                    Ok(self.literal_token(TokenType::Number, LiteralValue::Float(value)))
+                }
+                _ => Err(self.error(&format!("Float literal {} is out of range", self.lexeme()))),
+            };
+        }
+        // integers too large for an int are floats, as all numbers were
+        // before ints existed
+        match digits.parse() {
+            Ok(value) => Ok(self.literal_token(TokenType::Number, LiteralValue::Int(value))),
+            Err(_) => match digits.parse::<f64>() {
+                Ok(value) if value.is_finite() => {
+                    Ok(self.literal_token(TokenType::Number, LiteralValue::Float(value)))
+                }
+                _ => Err(self.error(&format!(
+                    "Integer literal {} is out of range",
+                    self.lexeme()
+                ))),
+            },
+        }
+    }
+
+    // `0xff`, `0o17` and `0b1010`, after the radix letter
+    fn radix_number(&mut self, radix: u32) -> Result<Token, TullyError> {
+        if !self.peek(0).is_digit(radix) {
+            return Err(self.error(&format!("Expected digits after {}", self.lexeme())));
+        }
+        let mut digits = String::new();
+        self.digits(&mut digits, radix)?;
+        // `d` is a hex digit, so only big integers take a radix
+        if self.peek(0) == 'n' && !(self.peek(1).is_alphanumeric() || self.peek(1) == '_') {
             self.eat();
-            while self.peek(0).is_digit(10) {
+            return self.big_number(digits, radix, 'n', false);
+        }
+        self.end_of_number()?;
+        match i64::from_str_radix(&digits, radix) {
+            Ok(value) => Ok(self.literal_token(TokenType::Number, LiteralValue::Int(value))),
+            Err(_) => Err(self.error(&format!("Integer literal {} is too large", self.lexeme()))),
+        }
+    }
+
+    // Eats the digits of `radix` into `digits`, leaving out the `_` that
+    // may separate two of them
+    fn digits(&mut self, digits: &mut String, radix: u32) -> Result<(), TullyError> {
+        loop {
+            let c = self.peek(0);
+            if c.is_digit(radix) {
+                digits.push(self.eat());
+            } else if c == '_' {
+                let after_digit = digits.chars().last().is_some_and(|c| c.is_digit(radix));
+                if !after_digit || !self.peek(1).is_digit(radix) {
+                    return Err(self.error(&format!(
+                        "'_' must be between two digits in {}_",
+                        self.lexeme()
+                    )));
+                }
                 self.eat();
+            } else {
+                return Ok(());
             }
         }
-        return self.token_type(TokenType::Number);
+    }
+
+    // `12abc` or `0b102` is a malformed number, not a number and a name
+    fn end_of_number(&self) -> Result<(), TullyError> {
+        let c = self.peek(0);
+        if c.is_alphanumeric() || c == '_' {
+            return Err(self.error(&format!(
+                "Unexpected character {} in number literal {}",
+                c,
+                self.lexeme()
+            )));
+        }
+        Ok(())
+    }
+
+    // `123n` is a big integer and `0.1d` an exact decimal
+    #[cfg(feature = "bignum")]
+    fn big_number(
+        &mut self,
+        digits: String,
+        radix: u32,
+        suffix: char,
+        fraction: bool,
+    ) -> Result<Token, TullyError> {
+        let literal = match suffix {
+            'n' if fraction => {
+                return Err(self.error("Big integer literal cannot have a fraction or an exponent"));
+            }
+            'n' => LiteralValue::BigInt(BigInt::parse_bytes(digits.as_bytes(), radix).unwrap()),
+            _ => LiteralValue::Decimal(digits.parse().unwrap()),
+        };
+        Ok(self.literal_token(TokenType::Number, literal))
+    }
+
+    #[cfg(not(feature = "bignum"))]
+    fn big_number(
+        &self,
+        _digits: String,
+        _radix: u32,
+        suffix: char,
+        _fraction: bool,
+    ) -> Result<Token, TullyError> {
+        Err(self.error(&format!(
+            "Number suffix '{}' needs tully built with the bignum feature",
+            suffix
+        )))
     }
 
     fn identifier(&mut self) -> Token {
@@ -98,39 +253,243 @@ impl Lexer {
         }
     }
 
+    // Scans string content up to the closing quote or up to the next `${`.
+    // The latter yields an `Interpolation` token holding the text so far;
+    // the string resumes after the `}` that closes the embedded expression.
     fn scan_string(&mut self) -> Result<Token, TullyError> {
-        while self.peek(0) != '"' && !self.is_at_end() {
-            if self.peek(0) == '\n' {
-                self.line += 1;
+        let mut value = String::new();
+        loop {
+            if self.is_at_end() {
+                return Err(self.error("Unterminated string"));
+            }
+            let c = self.eat();
+            match c {
+                '"' => break,
+                '\\' => value.push(self.escape()?),
+                '$' if self.peek(0) == '{' => {
+                    self.eat();
+                    self.interpolations.push(0);
+                    return Ok(
+                        self.literal_token(TokenType::Interpolation, LiteralValue::String(value))
+                    );
+                }
+                _ => {
+                    if c == '\n' {
+                        self.line += 1;
+                    }
+                    value.push(c);
+                }
             }
-            self.eat();
+        }
+        Ok(self.literal_token(TokenType::String, LiteralValue::String(value)))
+    }
+
+    fn escape(&mut self) -> Result<char, TullyError> {
+        let c = self.eat();
+        match c {
+            'n' => Ok('\n'),
+            't' => Ok('\t'),
+            'r' => Ok('\r'),
+            '0' => Ok('\0'),
+            '"' => Ok('"'),
+            '\\' => Ok('\\'),
+            '$' => Ok('$'),
+            'u' => self.unicode_escape(),
+            '\0' => Err(self.error("Unterminated string")),
+            _ => Err(self.error(&format!("Invalid escape sequence \\{}", c))),
+        }
+    }
+
+    fn unicode_escape(&mut self) -> Result<char, TullyError> {
+        if self.peek(0) != '{' {
+            return Err(self.error("Expecting '{' after \\u"));
+        }
+        self.eat();
+        let mut digits = String::new();
+        while self.peek(0) != '}' && !self.is_at_end() {
+            digits.push(self.eat());
         }
         if self.is_at_end() {
-            return Err(self.error("Unterminated string"));
+            return Err(self.error("Unterminated unicode escape"));
         }
         self.eat();
-        Ok(self.token_type(TokenType::String))
+        if digits.is_empty() || digits.len() > 6 {
+            return Err(self.error("Unicode escape must have 1 to 6 hex digits"));
+        }
+        match u32::from_str_radix(&digits, 16)
+            .ok()
+            .and_then(std::char::from_u32)
+        {
+            Some(c) => Ok(c),
+            None => Err(self.error(&format!("Invalid unicode escape \\u{{{}}}", digits))),
+        }
+    }
+
+    fn line_comment(&mut self) {
+        while self.peek(0) != '\n' && !self.is_at_end() {
+            self.eat();
+        }
+        self.start = self.current;
+    }
+
+    // block comments nest, so `/* a /* b */ c */` is a single comment
+    fn block_comment(&mut self) -> Result<(), TullyError> {
+        let line = self.line;
+        self.eat();
+        let mut depth = 1;
+        while !self.is_at_end() {
+            let c = self.eat();
+            if c == '\n' {
+                self.line += 1;
+            } else if c == '/' && self.peek(0) == '*' {
+                self.eat();
+                depth += 1;
+            } else if c == '*' && self.peek(0) == '/' {
+                self.eat();
+                depth -= 1;
+                if depth == 0 {
+                    self.start = self.current;
+                    return Ok(());
+                }
+            }
+        }
+        Err(TullyError::lexical_error_message(
+            line,
+            "Unterminated block comment",
+        ))
     }
 
     fn scan_token(&mut self) -> Result<Token, TullyError> {
         let c = self.space();
         match c {
-            '+' => Ok(self.token_type(TokenType::Plus)),
-            '-' => Ok(self.token_type(TokenType::Minus)),
-            '*' => Ok(self.token_type(TokenType::Star)),
-            '/' => Ok(self.token_type(TokenType::Slash)),
-            '%' => Ok(self.token_type(TokenType::Percentage)),
+            '+' => match self.peek(0) {
+                '=' => {
+                    self.eat();
+                    Ok(self.token_type(TokenType::PlusEqual))
+                }
+                '+' => {
+                    self.eat();
+                    Ok(self.token_type(TokenType::PlusPlus))
+                }
+                _ => Ok(self.token_type(TokenType::Plus)),
+            },
+            '-' => match self.peek(0) {
+                '=' => {
+                    self.eat();
+                    Ok(self.token_type(TokenType::MinusEqual))
+                }
+                '-' => {
+                    self.eat();
+                    Ok(self.token_type(TokenType::MinusMinus))
+                }
+                _ => Ok(self.token_type(TokenType::Minus)),
+            },
+            '*' => {
+                if self.peek(0) == '*' {
+                    self.eat();
+                    return Ok(self.token_type(TokenType::StarStar));
+                }
+                if self.peek(0) == '=' {
+                    self.eat();
+                    return Ok(self.token_type(TokenType::StarEqual));
+                }
+                Ok(self.token_type(TokenType::Star))
+            }
+            '/' => {
+                if self.peek(0) == '/' {
+                    self.line_comment();
+                    return self.next_token();
+                }
+                if self.peek(0) == '*' {
+                    self.block_comment()?;
+                    return self.next_token();
+                }
+                if self.peek(0) == '=' {
+                    self.eat();
+                    return Ok(self.token_type(TokenType::SlashEqual));
+                }
+                Ok(self.token_type(TokenType::Slash))
+            }
+            // floor division is `~/`, as `//` starts a comment; `~` before
+            // a comment is still a complement
+            '~' => {
+                if self.peek(0) == '/' && self.peek(1) != '*' && self.peek(1) != '/' {
+                    self.eat();
+                    return Ok(self.token_type(TokenType::TildeSlash));
+                }
+                Ok(self.token_type(TokenType::Tilde))
+            }
+            '%' => {
+                if self.peek(0) == '=' {
+                    self.eat();
+                    return Ok(self.token_type(TokenType::PercentageEqual));
+                }
+                Ok(self.token_type(TokenType::Percentage))
+            }
+            '|' => Ok(self.token_type(TokenType::Pipe)),
+            '&' => Ok(self.token_type(TokenType::Ampersand)),
+            '^' => Ok(self.token_type(TokenType::Caret)),
             '(' => Ok(self.token_type(TokenType::OpenParen)),
             ')' => Ok(self.token_type(TokenType::CloseParen)),
             ';' => Ok(self.token_type(TokenType::SemiColon)),
-            '{' => Ok(self.token_type(TokenType::OpenBrace)),
-            '}' => Ok(self.token_type(TokenType::CloseBrace)),
+            ':' => Ok(self.token_type(TokenType::Colon)),
+            '{' => {
+                if let Some(depth) = self.interpolations.last_mut() {
+                    *depth += 1;
+                }
+                Ok(self.token_type(TokenType::OpenBrace))
+            }
+            '}' => {
+                match self.interpolations.last_mut() {
+                    Some(0) => {
+                        self.interpolations.pop();
+                        return self.scan_string();
+                    }
+                    Some(depth) => *depth -= 1,
+                    None => (),
+                }
+                Ok(self.token_type(TokenType::CloseBrace))
+            }
+            '[' => Ok(self.token_type(TokenType::OpenBracket)),
+            ']' => Ok(self.token_type(TokenType::CloseBracket)),
             ',' => Ok(self.token_type(TokenType::COMMA)),
+            '.' => {
+                if self.peek(0) == '.' && self.peek(1) == '.' {
+                    self.eat();
+                    self.eat();
+                    return Ok(self.token_type(TokenType::Ellipsis));
+                }
+                if self.peek(0) == '.' && self.peek(1) == '=' {
+                    self.eat();
+                    self.eat();
+                    return Ok(self.token_type(TokenType::DotDotEqual));
+                }
+                if self.peek(0) == '.' {
+                    self.eat();
+                    return Ok(self.token_type(TokenType::DotDot));
+                }
+                Ok(self.token_type(TokenType::Dot))
+            }
+            '?' => {
+                if self.peek(0) == '?' {
+                    self.eat();
+                    return Ok(self.token_type(TokenType::QuestionQuestion));
+                }
+                if self.peek(0) == '.' {
+                    self.eat();
+                    return Ok(self.token_type(TokenType::QuestionDot));
+                }
+                Ok(self.token_type(TokenType::Question))
+            }
             '=' => {
                 if self.peek(0) == '=' {
                     self.eat();
                     return Ok(self.token_type(TokenType::EqualEqual));
                 }
+                if self.peek(0) == '>' {
+                    self.eat();
+                    return Ok(self.token_type(TokenType::Arrow));
+                }
                 Ok(self.token_type(TokenType::Equal))
             }
             '!' => {
@@ -145,6 +504,10 @@ impl Lexer {
                     self.eat();
                     return Ok(self.token_type(TokenType::GreaterEqual));
                 }
+                if self.peek(0) == '>' {
+                    self.eat();
+                    return Ok(self.token_type(TokenType::GreaterGreater));
+                }
                 Ok(self.token_type(TokenType::Greater))
             }
             '<' => {
@@ -152,14 +515,18 @@ impl Lexer {
                     self.eat();
                     return Ok(self.token_type(TokenType::LesserEqual));
                 }
+                if self.peek(0) == '<' {
+                    self.eat();
+                    return Ok(self.token_type(TokenType::LessLess));
+                }
                 Ok(self.token_type(TokenType::Lesser))
             }
             '"' => self.scan_string(),
             _ => {
                 if c.is_digit(10) {
-                    return Ok(self.number());
+                    return self.number();
                 }
-                if c.is_alphanumeric() {
+                if c.is_alphanumeric() || c == '_' {
                     return Ok(self.identifier());
                 }
                 return Err(self.error(&format!("Unexpected character {}", c)));
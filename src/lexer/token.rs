@@ -1,9 +1,13 @@
+use crate::value::LiteralValue;
+
 #[derive(Debug, Clone)]
 pub enum TokenType {
     Plus,
     Star,
+    StarStar,
     Minus,
     Slash,
+    TildeSlash,
     Percentage,
     EOL,
     Number,
@@ -14,10 +18,12 @@ pub enum TokenType {
     Nil,
     Print,
     Var,
+    Const,
     String,
     Identifier,
     Equal,
     EqualEqual,
+    Arrow,
     Bang,
     BangEqual,
     Greater,
@@ -25,8 +31,11 @@ pub enum TokenType {
     Lesser,
     LesserEqual,
     SemiColon,
+    Colon,
     OpenBrace,
     CloseBrace,
+    OpenBracket,
+    CloseBracket,
     If,
     Else,
     While,
@@ -34,6 +43,42 @@ pub enum TokenType {
     COMMA,
     Fun,
     Return,
+    Break,
+    Continue,
+    And,
+    Or,
+    Interpolation,
+    Class,
+    This,
+    Dot,
+    Super,
+    Throw,
+    Try,
+    Catch,
+    Finally,
+    Import,
+    Match,
+    In,
+    Yield,
+    Pipe,
+    PlusEqual,
+    MinusEqual,
+    StarEqual,
+    SlashEqual,
+    PercentageEqual,
+    PlusPlus,
+    MinusMinus,
+    Question,
+    QuestionQuestion,
+    QuestionDot,
+    Ellipsis,
+    DotDot,
+    DotDotEqual,
+    Ampersand,
+    Caret,
+    Tilde,
+    LessLess,
+    GreaterGreater,
 }
 
 #[derive(Debug, Clone)]
@@ -41,6 +86,7 @@ pub struct Token {
     pub tt: TokenType,
     pub lexeme: String,
     pub line: usize,
+    pub literal: Option<LiteralValue>,
     start: usize,
     end: usize,
 }
@@ -51,6 +97,7 @@ impl Token {
             tt: TokenType::EOL,
             lexeme: String::default(),
             line: 0,
+            literal: None,
             start: 0,
             end: 0,
         };
@@ -60,8 +107,14 @@ impl Token {
             tt,
             lexeme,
             line,
+            literal: None,
             start,
             end,
         }
     }
+
+    pub fn with_literal(mut self, literal: LiteralValue) -> Token {
+        self.literal = Some(literal);
+        self
+    }
 }
@@ -5,6 +5,8 @@ mod environment;
 mod error;
 pub mod evaluator;
 mod lexer;
+pub mod loader;
 mod parser;
+mod resolver;
 mod tully;
 mod value;
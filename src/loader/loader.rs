@@ -0,0 +1,70 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+// Where `import` finds the source of a module. Modules are identified by
+// the name `resolve` gives them, every name is loaded and run only once.
+pub trait ModuleLoader {
+    // Name of the module imported as `path` from the module named `from`,
+    // or from the main script when it has no name. By default relative
+    // paths are taken from the directory of the importing module.
+    fn resolve(&self, from: Option<&str>, path: &str) -> String {
+        let base = match from.and_then(|from| Path::new(from).parent()) {
+            Some(parent) => parent.join(path),
+            None => PathBuf::from(path),
+        };
+        let mut normalized = PathBuf::new();
+        for component in base.components() {
+            match component {
+                Component::CurDir => (),
+                Component::ParentDir => match normalized.components().next_back() {
+                    Some(Component::Normal(_)) => {
+                        normalized.pop();
+                    }
+                    Some(Component::RootDir) => (),
+                    _ => normalized.push(".."),
+                },
+                component => normalized.push(component),
+            }
+        }
+        normalized.to_string_lossy().into_owned()
+    }
+
+    fn load(&self, name: &str) -> Result<String, String>;
+}
+
+// Reads modules from disk, the default loader
+pub struct FileLoader;
+
+impl ModuleLoader for FileLoader {
+    fn load(&self, name: &str) -> Result<String, String> {
+        fs::read_to_string(name).map_err(|e| e.to_string())
+    }
+}
+
+// Serves modules from sources registered by the host, for embedding
+// without a file system
+#[derive(Default)]
+pub struct MemoryLoader {
+    modules: HashMap<String, String>,
+}
+
+impl MemoryLoader {
+    pub fn new() -> MemoryLoader {
+        MemoryLoader::default()
+    }
+
+    pub fn insert(&mut self, name: &str, source: &str) {
+        self.modules
+            .insert(String::from(name), String::from(source));
+    }
+}
+
+impl ModuleLoader for MemoryLoader {
+    fn load(&self, name: &str) -> Result<String, String> {
+        match self.modules.get(name) {
+            Some(source) => Ok(source.clone()),
+            None => Err(String::from("no such module")),
+        }
+    }
+}
@@ -0,0 +1,5 @@
+pub use loader::FileLoader;
+pub use loader::MemoryLoader;
+pub use loader::ModuleLoader;
+
+mod loader;
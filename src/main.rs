@@ -52,8 +52,11 @@ fn read_from_file(name: &String) {
     let contents = fs::read_to_string(name);
     match contents {
         Ok(source) => {
+            let path = name.clone();
             let handler = thread::spawn(move || {
-                if let Err(e) = Evaluator::new().eval(&source) {
+                let mut evaluator = Evaluator::new();
+                evaluator.set_path(&path);
+                if let Err(e) = evaluator.eval(&source) {
                     eprintln!("{}", e.to_string());
                 }
             });
@@ -1,3 +1,6 @@
+use std::cell::Cell;
+use std::rc::Rc;
+
 use crate::lexer::token::Token;
 use crate::value::LiteralValue;
 
@@ -5,6 +8,14 @@ pub trait Expr {
     fn accept<V>(self, visitor: impl Visitor<V>) -> V;
 }
 
+// Where the resolver found a local variable: `depth` scopes out from the
+// one the access happens in, at position `index` of that scope.
+#[derive(Clone, Copy, Debug)]
+pub struct Slot {
+    pub depth: usize,
+    pub index: usize,
+}
+
 pub trait Visitor<T> {
     fn visit_binary_operation(&mut self, expr: &Binary) -> T;
     fn visit_literal(&mut self, expr: &Literal) -> T;
@@ -19,8 +30,32 @@ pub trait Visitor<T> {
     fn visit_if_statement(&mut self, expr: &IfStatement) -> T;
     fn visit_while_statement(&mut self, expr: &WhileStatement) -> T;
     fn visit_call(&mut self, expr: &Call) -> T;
-    fn visit_function(&mut self, expr: &Function) -> T;
+    fn visit_function(&mut self, expr: &Rc<Function>) -> T;
     fn visit_return(&mut self, expr: &Return) -> T;
+    fn visit_logical(&mut self, expr: &Logical) -> T;
+    fn visit_class(&mut self, expr: &Class) -> T;
+    fn visit_get(&mut self, expr: &Get) -> T;
+    fn visit_set(&mut self, expr: &Set) -> T;
+    fn visit_this(&mut self, expr: &This) -> T;
+    fn visit_super(&mut self, expr: &Super) -> T;
+    fn visit_list(&mut self, expr: &List) -> T;
+    fn visit_index(&mut self, expr: &Index) -> T;
+    fn visit_set_index(&mut self, expr: &SetIndex) -> T;
+    fn visit_map(&mut self, expr: &Map) -> T;
+    fn visit_break(&mut self, expr: &Break) -> T;
+    fn visit_continue(&mut self, expr: &Continue) -> T;
+    fn visit_lambda(&mut self, expr: &Rc<Function>) -> T;
+    fn visit_throw(&mut self, expr: &Throw) -> T;
+    fn visit_try(&mut self, expr: &Try) -> T;
+    fn visit_import(&mut self, expr: &Import) -> T;
+    fn visit_match(&mut self, expr: &Match) -> T;
+    fn visit_compound(&mut self, expr: &Compound) -> T;
+    fn visit_conditional(&mut self, expr: &Conditional) -> T;
+    fn visit_spread(&mut self, expr: &Spread) -> T;
+    fn visit_range(&mut self, expr: &Range) -> T;
+    fn visit_for_in(&mut self, expr: &ForIn) -> T;
+    fn visit_yield(&mut self, expr: &Yield) -> T;
+    fn visit_optional_chain(&mut self, expr: &OptionalChain) -> T;
 }
 
 #[derive(Clone, Debug)]
@@ -45,8 +80,32 @@ pub enum ExprType {
     IfStatement(IfStatement),
     WhileStatement(WhileStatement),
     Call(Call),
-    Function(Function),
+    Function(Rc<Function>),
     Return(Return),
+    Logical(Logical),
+    Class(Class),
+    Get(Get),
+    Set(Set),
+    This(This),
+    Super(Super),
+    List(List),
+    Index(Index),
+    SetIndex(SetIndex),
+    Map(Map),
+    Break(Break),
+    Continue(Continue),
+    Lambda(Rc<Function>),
+    Throw(Throw),
+    Try(Try),
+    Import(Import),
+    Match(Match),
+    Compound(Compound),
+    Conditional(Conditional),
+    Spread(Spread),
+    Range(Range),
+    ForIn(ForIn),
+    Yield(Yield),
+    OptionalChain(OptionalChain),
 }
 
 impl Expr for Binary {
@@ -114,6 +173,8 @@ impl Expr for Print {
 #[derive(Debug, Clone)]
 pub struct Variable {
     pub name: Token,
+    // `None` for globals, filled in by the resolver
+    pub slot: Cell<Option<Slot>>,
 }
 
 impl Expr for Variable {
@@ -124,8 +185,12 @@ impl Expr for Variable {
 
 #[derive(Clone, Debug)]
 pub struct Var {
-    pub name: String,
+    pub name: Token,
     pub initializer: Option<Box<ExprType>>,
+    // declared with `const`, it can not be reassigned
+    pub constant: bool,
+    // index in the declaring scope, `None` for globals
+    pub slot: Cell<Option<usize>>,
 }
 
 impl Expr for Var {
@@ -138,6 +203,7 @@ impl Expr for Var {
 pub struct Assign {
     pub name: Token,
     pub initializer: Box<ExprType>,
+    pub slot: Cell<Option<Slot>>,
 }
 
 impl Expr for Assign {
@@ -174,6 +240,9 @@ impl Expr for IfStatement {
 pub struct WhileStatement {
     pub condition: Box<ExprType>,
     pub body: Box<ExprType>,
+    // the increment clause of a `for` loop, run after every iteration
+    // including the ones cut short by `continue`
+    pub increment: Option<Box<ExprType>>,
 }
 
 impl Expr for WhileStatement {
@@ -198,11 +267,30 @@ impl Expr for Call {
 #[derive(Clone, Debug)]
 pub struct Function {
     pub name: Token,
-    pub params: Vec<Token>,
+    pub params: Vec<Param>,
     pub body: Block,
+    // declared with `fun*`, calls return a generator
+    pub generator: bool,
+    pub slot: Cell<Option<usize>>,
+}
+
+impl Function {
+    // the fewest and the most arguments a call may pass, no most when the
+    // last parameter is a rest parameter
+    pub fn arity(&self) -> (usize, Option<usize>) {
+        let required = self
+            .params
+            .iter()
+            .filter(|param| param.default.is_none() && !param.rest)
+            .count();
+        match self.params.last() {
+            Some(param) if param.rest => (required, None),
+            _ => (required, Some(self.params.len())),
+        }
+    }
 }
 
-impl Expr for Function {
+impl Expr for Rc<Function> {
     fn accept<V>(self, mut visitor: impl Visitor<V>) -> V {
         return visitor.visit_function(&self);
     }
@@ -219,3 +307,370 @@ impl Expr for Return {
         return visitor.visit_return(&self);
     }
 }
+
+#[derive(Clone, Debug)]
+pub struct Logical {
+    pub left: Box<ExprType>,
+    pub right: Box<ExprType>,
+    pub operator: Token,
+}
+
+impl Expr for Logical {
+    fn accept<V>(self, mut visitor: impl Visitor<V>) -> V {
+        return visitor.visit_logical(&self);
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Class {
+    pub name: Token,
+    pub superclass: Option<Variable>,
+    pub methods: Vec<Rc<Function>>,
+    pub slot: Cell<Option<usize>>,
+}
+
+impl Expr for Class {
+    fn accept<V>(self, mut visitor: impl Visitor<V>) -> V {
+        return visitor.visit_class(&self);
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Get {
+    pub object: Box<ExprType>,
+    pub name: Token,
+    // `object?.name`, skips the rest of the optional chain around it when
+    // the object is nil
+    pub optional: bool,
+}
+
+impl Expr for Get {
+    fn accept<V>(self, mut visitor: impl Visitor<V>) -> V {
+        return visitor.visit_get(&self);
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Set {
+    pub object: Box<ExprType>,
+    pub name: Token,
+    pub value: Box<ExprType>,
+}
+
+impl Expr for Set {
+    fn accept<V>(self, mut visitor: impl Visitor<V>) -> V {
+        return visitor.visit_set(&self);
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct This {
+    pub keyword: Token,
+    pub slot: Cell<Option<Slot>>,
+}
+
+impl Expr for This {
+    fn accept<V>(self, mut visitor: impl Visitor<V>) -> V {
+        return visitor.visit_this(&self);
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Super {
+    pub keyword: Token,
+    pub method: Token,
+    // slot of `super`; `this` lives one scope further in
+    pub slot: Cell<Option<Slot>>,
+}
+
+impl Expr for Super {
+    fn accept<V>(self, mut visitor: impl Visitor<V>) -> V {
+        return visitor.visit_super(&self);
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct List {
+    pub bracket: Token,
+    pub elements: Vec<Box<ExprType>>,
+}
+
+impl Expr for List {
+    fn accept<V>(self, mut visitor: impl Visitor<V>) -> V {
+        return visitor.visit_list(&self);
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Index {
+    pub object: Box<ExprType>,
+    pub bracket: Token,
+    pub index: Box<ExprType>,
+}
+
+impl Expr for Index {
+    fn accept<V>(self, mut visitor: impl Visitor<V>) -> V {
+        return visitor.visit_index(&self);
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct SetIndex {
+    pub object: Box<ExprType>,
+    pub bracket: Token,
+    pub index: Box<ExprType>,
+    pub value: Box<ExprType>,
+}
+
+impl Expr for SetIndex {
+    fn accept<V>(self, mut visitor: impl Visitor<V>) -> V {
+        return visitor.visit_set_index(&self);
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Map {
+    pub brace: Token,
+    pub entries: Vec<(Box<ExprType>, Box<ExprType>)>,
+}
+
+impl Expr for Map {
+    fn accept<V>(self, mut visitor: impl Visitor<V>) -> V {
+        return visitor.visit_map(&self);
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Break {
+    pub keyword: Token,
+}
+
+impl Expr for Break {
+    fn accept<V>(self, mut visitor: impl Visitor<V>) -> V {
+        return visitor.visit_break(&self);
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Continue {
+    pub keyword: Token,
+}
+
+impl Expr for Continue {
+    fn accept<V>(self, mut visitor: impl Visitor<V>) -> V {
+        return visitor.visit_continue(&self);
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Throw {
+    pub keyword: Token,
+    pub value: Box<ExprType>,
+}
+
+impl Expr for Throw {
+    fn accept<V>(self, mut visitor: impl Visitor<V>) -> V {
+        return visitor.visit_throw(&self);
+    }
+}
+
+// `catch (name) { body }`, the name may be left out
+#[derive(Clone, Debug)]
+pub struct Catch {
+    pub name: Option<Token>,
+    pub body: Block,
+    pub slot: Cell<Option<usize>>,
+}
+
+#[derive(Clone, Debug)]
+pub struct Try {
+    pub keyword: Token,
+    pub body: Block,
+    pub catch: Option<Catch>,
+    pub finally: Option<Block>,
+}
+
+impl Expr for Try {
+    fn accept<V>(self, mut visitor: impl Visitor<V>) -> V {
+        return visitor.visit_try(&self);
+    }
+}
+
+// `import "path" as alias;` binds the module itself and
+// `from "path" import a, b;` binds the listed names it exports
+#[derive(Clone, Debug)]
+pub struct Import {
+    pub keyword: Token,
+    pub path: Token,
+    pub alias: Option<Token>,
+    pub names: Vec<Token>,
+    // one per bound name, the alias or each of `names`
+    pub slots: Vec<Cell<Option<usize>>>,
+}
+
+impl Expr for Import {
+    fn accept<V>(self, mut visitor: impl Visitor<V>) -> V {
+        return visitor.visit_import(&self);
+    }
+}
+
+#[derive(Clone, Debug)]
+pub enum Pattern {
+    // `_`, matches anything
+    Wildcard,
+    // a literal, matched with `Value::equals`
+    Literal(Box<ExprType>),
+    // a name, matches anything and binds it
+    Binding(Token, Cell<Option<usize>>),
+    // `[a, b]`, matches lists of exactly that length
+    List(Vec<Pattern>),
+    // `{"key": a}`, matches maps holding at least those keys
+    Map(Vec<(Box<ExprType>, Pattern)>),
+}
+
+impl Pattern {
+    // the names the pattern binds, in order
+    pub fn bindings(&self) -> Vec<&Token> {
+        match self {
+            Pattern::Wildcard | Pattern::Literal(_) => Vec::new(),
+            Pattern::Binding(name, _) => vec![name],
+            Pattern::List(patterns) => patterns.iter().flat_map(Pattern::bindings).collect(),
+            Pattern::Map(entries) => entries
+                .iter()
+                .flat_map(|(_, pattern)| pattern.bindings())
+                .collect(),
+        }
+    }
+}
+
+// `pattern | pattern if guard => body`
+#[derive(Clone, Debug)]
+pub struct MatchArm {
+    pub patterns: Vec<Pattern>,
+    pub guard: Option<Box<ExprType>>,
+    pub body: Box<ExprType>,
+}
+
+#[derive(Clone, Debug)]
+pub struct Match {
+    pub keyword: Token,
+    pub subject: Box<ExprType>,
+    pub arms: Vec<MatchArm>,
+}
+
+impl Expr for Match {
+    fn accept<V>(self, mut visitor: impl Visitor<V>) -> V {
+        return visitor.visit_match(&self);
+    }
+}
+
+// `target += value` and the other compound assignments, `++target` and
+// `target++`. The target is a variable, an index or a field, and its
+// object and index are evaluated only once.
+#[derive(Clone, Debug)]
+pub struct Compound {
+    pub target: Box<ExprType>,
+    // the binary operator applied, `+` for both `+=` and `++`
+    pub operator: Token,
+    pub value: Box<ExprType>,
+    // postfix `++` and `--` evaluate to the value before the update
+    pub postfix: bool,
+}
+
+impl Expr for Compound {
+    fn accept<V>(self, mut visitor: impl Visitor<V>) -> V {
+        return visitor.visit_compound(&self);
+    }
+}
+
+// `condition ? then_branch : else_branch`
+#[derive(Clone, Debug)]
+pub struct Conditional {
+    pub condition: Box<ExprType>,
+    pub then_branch: Box<ExprType>,
+    pub else_branch: Box<ExprType>,
+}
+
+impl Expr for Conditional {
+    fn accept<V>(self, mut visitor: impl Visitor<V>) -> V {
+        return visitor.visit_conditional(&self);
+    }
+}
+
+// `name`, `name = default` or `...name` in a parameter list
+#[derive(Clone, Debug)]
+pub struct Param {
+    pub name: Token,
+    pub default: Option<Box<ExprType>>,
+    pub rest: bool,
+}
+
+// `...value` in the arguments of a call
+#[derive(Clone, Debug)]
+pub struct Spread {
+    pub ellipsis: Token,
+    pub value: Box<ExprType>,
+}
+
+impl Expr for Spread {
+    fn accept<V>(self, mut visitor: impl Visitor<V>) -> V {
+        return visitor.visit_spread(&self);
+    }
+}
+
+// `start..end`, `start..=end`, optionally followed by `step n`
+#[derive(Clone, Debug)]
+pub struct Range {
+    pub start: Box<ExprType>,
+    pub operator: Token,
+    pub end: Box<ExprType>,
+    pub step: Option<Box<ExprType>>,
+}
+
+impl Expr for Range {
+    fn accept<V>(self, mut visitor: impl Visitor<V>) -> V {
+        return visitor.visit_range(&self);
+    }
+}
+
+// `for (name in iterable) body`, every iteration binds `name` in a
+// scope of its own
+#[derive(Clone, Debug)]
+pub struct ForIn {
+    pub name: Token,
+    pub keyword: Token,
+    pub iterable: Box<ExprType>,
+    pub body: Box<ExprType>,
+}
+
+impl Expr for ForIn {
+    fn accept<V>(self, mut visitor: impl Visitor<V>) -> V {
+        return visitor.visit_for_in(&self);
+    }
+}
+
+// `yield value;`, only allowed as a statement of a generator
+#[derive(Clone, Debug)]
+pub struct Yield {
+    pub keyword: Token,
+    pub value: Option<Box<ExprType>>,
+}
+
+impl Expr for Yield {
+    fn accept<V>(self, mut visitor: impl Visitor<V>) -> V {
+        return visitor.visit_yield(&self);
+    }
+}
+
+// A chain of calls, indexes and property accesses with a `?.` in it,
+// which is nil as soon as one of its `?.` finds nil
+#[derive(Clone, Debug)]
+pub struct OptionalChain {
+    pub expression: Box<ExprType>,
+}
+
+impl Expr for OptionalChain {
+    fn accept<V>(self, mut visitor: impl Visitor<V>) -> V {
+        return visitor.visit_optional_chain(&self);
+    }
+}
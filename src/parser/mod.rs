@@ -1,20 +1,48 @@
 pub use expr::Assign;
 pub use expr::Binary;
 pub use expr::Block;
+pub use expr::Break;
 pub use expr::Call;
+pub use expr::Catch;
+pub use expr::Class;
+pub use expr::Compound;
+pub use expr::Conditional;
+pub use expr::Continue;
 pub use expr::ExprType;
 pub use expr::Expression;
+pub use expr::ForIn;
 pub use expr::Function;
+pub use expr::Get;
 pub use expr::Group;
 pub use expr::IfStatement;
+pub use expr::Import;
+pub use expr::Index;
+pub use expr::List;
 pub use expr::Literal;
+pub use expr::Logical;
+pub use expr::Map;
+pub use expr::Match;
+pub use expr::MatchArm;
+pub use expr::OptionalChain;
+pub use expr::Param;
+pub use expr::Pattern;
 pub use expr::Print;
+pub use expr::Range;
 pub use expr::Return;
+pub use expr::Set;
+pub use expr::SetIndex;
+pub use expr::Slot;
+pub use expr::Spread;
+pub use expr::Super;
+pub use expr::This;
+pub use expr::Throw;
+pub use expr::Try;
 pub use expr::Unary;
 pub use expr::Var;
 pub use expr::Variable;
 pub use expr::Visitor;
 pub use expr::WhileStatement;
+pub use expr::Yield;
 pub use parser::Parser;
 
 mod expr;
@@ -1,14 +1,22 @@
 use std::cell::Cell;
+use std::rc::Rc;
 
 use crate::error::TullyError;
 use crate::lexer::token::TokenType::{
-    Bang, BangEqual, CloseBrace, CloseParen, Else, Equal, EqualEqual, Greater, GreaterEqual,
-    Identifier, Lesser, LesserEqual, Minus, OpenBrace, OpenParen, Plus, Print, SemiColon, Slash,
-    Star, COMMA,
+    Ampersand, Arrow, Bang, BangEqual, Caret, CloseBrace, CloseParen, Colon, DotDot, DotDotEqual,
+    Ellipsis, Else, Equal, EqualEqual, Greater, GreaterEqual, GreaterGreater, Identifier, LessLess,
+    Lesser, LesserEqual, Minus, MinusEqual, MinusMinus, OpenBrace, OpenParen, Percentage,
+    PercentageEqual, Pipe, Plus, PlusEqual, PlusPlus, Print, Question, QuestionDot,
+    QuestionQuestion, SemiColon, Slash, SlashEqual, Star, StarEqual, StarStar, Tilde, TildeSlash,
+    COMMA,
 };
 use crate::lexer::token::{Token, TokenType};
 use crate::lexer::Lexer;
-use crate::parser::expr::{Binary, Call, ExprType, Function, Group, Literal, Return};
+use crate::parser::expr::{
+    Binary, Break, Call, Catch, Class, Compound, Conditional, Continue, ExprType, ForIn, Function,
+    Get, Group, Import, Index, List, Literal, Logical, Map, Match, MatchArm, OptionalChain, Param,
+    Pattern, Range, Return, Set, SetIndex, Spread, Super, This, Throw, Try, Yield,
+};
 use crate::parser::{
     self, Assign, Block, Expression, IfStatement, Unary, Var, Variable, WhileStatement,
 };
@@ -18,6 +26,11 @@ pub struct Parser {
     pub source: String,
     n: Cell<usize>,
     tokens: Vec<Token>,
+    in_initializer: Cell<bool>,
+    loop_depth: Cell<usize>,
+    // at the top level of a match guard, where `(...) =>` ends the guard
+    // instead of starting an arrow function
+    in_guard: Cell<bool>,
 }
 
 impl Parser {
@@ -26,6 +39,9 @@ impl Parser {
             source,
             n: Cell::new(0),
             tokens: Vec::new(),
+            in_initializer: Cell::new(false),
+            loop_depth: Cell::new(0),
+            in_guard: Cell::new(false),
         }
     }
 
@@ -48,11 +64,30 @@ impl Parser {
             return self.print_statement();
         }
         if self.match_token(&[TokenType::Var]) {
-            return self.var_statement();
+            return self.var_statement(false);
         }
-        if self.match_token(&[TokenType::Fun]) {
+        if self.match_token(&[TokenType::Const]) {
+            return self.var_statement(true);
+        }
+        if self.check(&TokenType::Fun) && self.check_next(&Identifier) {
+            self.increment();
             return self.function(String::from("function"));
         }
+        // `fun* name` declares a generator, `fun* (` is a generator lambda
+        if self.check(&TokenType::Fun) && self.check_next(&Star) {
+            let start = self.n.get();
+            self.n.set(start + 2);
+            if self.check(&Identifier) {
+                return self.function(String::from("generator"));
+            }
+            self.n.set(start);
+        }
+        if self.match_token(&[TokenType::Yield]) {
+            return self.yield_statement();
+        }
+        if self.match_token(&[TokenType::Class]) {
+            return self.class_declaration();
+        }
         if self.match_token(&[TokenType::OpenBrace]) {
             return self.block();
         }
@@ -68,6 +103,31 @@ impl Parser {
         if self.match_token(&[TokenType::Return]) {
             return self.return_statement();
         }
+        if self.match_token(&[TokenType::Break, TokenType::Continue]) {
+            return self.loop_jump();
+        }
+        if self.match_token(&[TokenType::Import]) {
+            return self.import_statement();
+        }
+        // a match used as a statement needs no `;` after its closing brace
+        if self.check(&TokenType::Match) {
+            let expression = self.expression()?;
+            self.match_token(&[SemiColon]);
+            return Ok(ExprType::ExpressionStmt(Expression {
+                expression: Box::new(expression),
+            }));
+        }
+        // `from` is only a keyword in front of a module path
+        if self.check_word("from") && self.check_next(&TokenType::String) {
+            self.increment();
+            return self.from_import_statement();
+        }
+        if self.match_token(&[TokenType::Throw]) {
+            return self.throw_statement();
+        }
+        if self.match_token(&[TokenType::Try]) {
+            return self.try_statement();
+        }
         self.expression_statement()
     }
 
@@ -75,30 +135,308 @@ impl Parser {
         let keyword = self.previous().clone();
         let mut value = None;
         if !self.check(&SemiColon) {
+            if self.in_initializer.get() {
+                return Err(Parser::error(
+                    Some(&keyword),
+                    "Cannot return a value from an initializer",
+                ));
+            }
             value = Some(Box::new(self.expression()?));
         }
         self.expect_token_or(SemiColon, "Expecting ';' after return value")?;
         return Ok(ExprType::Return(Return { keyword, value }));
     }
 
-    fn var_statement(&self) -> Result<ExprType, TullyError> {
+    fn import_statement(&self) -> Result<ExprType, TullyError> {
+        let keyword = self.previous().clone();
+        let path = self.module_path()?;
+        if !self.check_word("as") {
+            return Err(Parser::error(self.peek(), "Expect 'as' after module path"));
+        }
+        self.increment();
+        let alias = self
+            .expect_token_or(Identifier, "Expect module name after 'as'")?
+            .clone();
+        self.expect_token_or(SemiColon, "Expecting ';' after import")?;
+        Ok(ExprType::Import(Import {
+            keyword,
+            path,
+            alias: Some(alias),
+            names: Vec::new(),
+            slots: vec![Cell::new(None)],
+        }))
+    }
+
+    fn from_import_statement(&self) -> Result<ExprType, TullyError> {
+        let keyword = self.previous().clone();
+        let path = self.module_path()?;
+        self.expect_token_or(TokenType::Import, "Expect 'import' after module path")?;
+        let mut names = Vec::new();
+        loop {
+            names.push(
+                self.expect_token_or(Identifier, "Expect name to import")?
+                    .clone(),
+            );
+            if !self.match_token(&[COMMA]) {
+                break;
+            }
+        }
+        self.expect_token_or(SemiColon, "Expecting ';' after import")?;
+        let slots = names.iter().map(|_| Cell::new(None)).collect();
+        Ok(ExprType::Import(Import {
+            keyword,
+            path,
+            alias: None,
+            names,
+            slots,
+        }))
+    }
+
+    fn module_path(&self) -> Result<Token, TullyError> {
+        Ok(self
+            .expect_token_or(TokenType::String, "Expect module path string")?
+            .clone())
+    }
+
+    fn match_expression(&self) -> Result<ExprType, TullyError> {
+        let keyword = self.previous().clone();
+        self.expect_token_or(OpenParen, "Expect '(' after 'match'")?;
+        let subject = self.expression()?;
+        self.expect_token_or(CloseParen, "Expect ')' after match value")?;
+        self.expect_token_or(OpenBrace, "Expect '{' before match arms")?;
+        let mut arms = Vec::new();
+        while !self.check(&CloseBrace) && !self.at_end() {
+            let arm = self.match_arm()?;
+            let block = matches!(*arm.body, ExprType::Block(_));
+            arms.push(arm);
+            // arms with a block body don't need a comma after them
+            if !self.match_token(&[COMMA]) && !block {
+                break;
+            }
+        }
+        self.expect_token_or(CloseBrace, "Expect '}' after match arms")?;
+        Ok(ExprType::Match(Match {
+            keyword,
+            subject: Box::new(subject),
+            arms,
+        }))
+    }
+
+    fn match_arm(&self) -> Result<MatchArm, TullyError> {
+        let names = |pattern: &Pattern| {
+            let mut names: Vec<String> = pattern
+                .bindings()
+                .iter()
+                .map(|name| name.lexeme.clone())
+                .collect();
+            names.sort();
+            names
+        };
+        let mut patterns = vec![self.pattern()?];
+        while self.match_token(&[Pipe]) {
+            let pipe = self.previous().clone();
+            let pattern = self.pattern()?;
+            if names(&pattern) != names(&patterns[0]) {
+                return Err(Parser::error(
+                    Some(&pipe),
+                    "Alternative patterns must bind the same names",
+                ));
+            }
+            patterns.push(pattern);
+        }
+        let mut guard = None;
+        if self.match_token(&[TokenType::If]) {
+            let enclosing = self.in_guard.replace(true);
+            let condition = self.assignment();
+            self.in_guard.set(enclosing);
+            guard = Some(Box::new(condition?));
+        }
+        self.expect_token_or(Arrow, "Expect '=>' after match pattern")?;
+        let body = if self.match_token(&[OpenBrace]) {
+            self.block()?
+        } else {
+            self.expression()?
+        };
+        Ok(MatchArm {
+            patterns,
+            guard,
+            body: Box::new(body),
+        })
+    }
+
+    fn pattern(&self) -> Result<Pattern, TullyError> {
+        if self.match_token(&[Identifier]) {
+            let name = self.previous().clone();
+            if name.lexeme == "_" {
+                return Ok(Pattern::Wildcard);
+            }
+            return Ok(Pattern::Binding(name, Cell::new(None)));
+        }
+        if self.check(&Minus) && self.check_next(&TokenType::Number) {
+            self.increment();
+            let operator = self.previous().clone();
+            return Ok(Pattern::Literal(Box::new(ExprType::Unary(Unary {
+                expression: Box::new(self.term()?),
+                operator,
+            }))));
+        }
+        if self.check_literal() || self.check(&TokenType::Nil) {
+            return Ok(Pattern::Literal(Box::new(self.term()?)));
+        }
+        if self.match_token(&[TokenType::OpenBracket]) {
+            let mut patterns = Vec::new();
+            while !self.check(&TokenType::CloseBracket) {
+                patterns.push(self.pattern()?);
+                if !self.match_token(&[COMMA]) {
+                    break;
+                }
+            }
+            self.expect_token_or(TokenType::CloseBracket, "Expect ']' after list pattern")?;
+            return Ok(Pattern::List(patterns));
+        }
+        if self.match_token(&[OpenBrace]) {
+            let mut entries = Vec::new();
+            while !self.check(&CloseBrace) {
+                if !self.check_literal() {
+                    return Err(Parser::error(
+                        self.peek(),
+                        "Expect literal key in map pattern",
+                    ));
+                }
+                let key = self.term()?;
+                self.expect_token_or(TokenType::Colon, "Expect ':' after map pattern key")?;
+                entries.push((Box::new(key), self.pattern()?));
+                if !self.match_token(&[COMMA]) {
+                    break;
+                }
+            }
+            self.expect_token_or(CloseBrace, "Expect '}' after map pattern")?;
+            return Ok(Pattern::Map(entries));
+        }
+        Err(Parser::error(self.peek(), "Expect pattern"))
+    }
+
+    // a number, string or boolean literal, what map patterns use as keys
+    fn check_literal(&self) -> bool {
+        self.check(&TokenType::Number)
+            || self.check(&TokenType::String)
+            || self.check(&TokenType::True)
+            || self.check(&TokenType::False)
+    }
+
+    fn throw_statement(&self) -> Result<ExprType, TullyError> {
+        let keyword = self.previous().clone();
+        let value = self.expression()?;
+        self.expect_token_or(SemiColon, "Expecting ';' after thrown value")?;
+        Ok(ExprType::Throw(Throw {
+            keyword,
+            value: Box::new(value),
+        }))
+    }
+
+    fn try_statement(&self) -> Result<ExprType, TullyError> {
+        let keyword = self.previous().clone();
+        let body = self.block_body("try")?;
+        let mut catch = None;
+        if self.match_token(&[TokenType::Catch]) {
+            let mut name = None;
+            if self.match_token(&[OpenParen]) {
+                self.expect_token_or(Identifier, "Expect error name after 'catch ('")?;
+                name = Some(self.previous().clone());
+                self.expect_token_or(CloseParen, "Expect ')' after error name")?;
+            }
+            catch = Some(Catch {
+                name,
+                body: self.block_body("catch")?,
+                slot: Cell::new(None),
+            });
+        }
+        let mut finally = None;
+        if self.match_token(&[TokenType::Finally]) {
+            finally = Some(self.block_body("finally")?);
+        }
+        if catch.is_none() && finally.is_none() {
+            return Err(Parser::error(
+                self.peek(),
+                "Expecting 'catch' or 'finally' after try block",
+            ));
+        }
+        Ok(ExprType::Try(Try {
+            keyword,
+            body,
+            catch,
+            finally,
+        }))
+    }
+
+    fn block_body(&self, kind: &str) -> Result<Block, TullyError> {
+        self.expect_token_or(OpenBrace, &format!("Expect '{{' before {} block", kind))?;
+        match self.block()? {
+            ExprType::Block(block) => Ok(block),
+            _ => Err(Parser::error(self.peek(), "Expecting block")),
+        }
+    }
+
+    fn loop_jump(&self) -> Result<ExprType, TullyError> {
+        let keyword = self.previous().clone();
+        if self.loop_depth.get() == 0 {
+            return Err(Parser::error(
+                Some(&keyword),
+                &format!("Cannot use '{}' outside of a loop", keyword.lexeme),
+            ));
+        }
+        self.expect_token_or(
+            SemiColon,
+            &format!("Expecting ';' after {}", keyword.lexeme),
+        )?;
+        match keyword.tt {
+            TokenType::Break => Ok(ExprType::Break(Break { keyword })),
+            _ => Ok(ExprType::Continue(Continue { keyword })),
+        }
+    }
+
+    fn loop_body(&self) -> Result<ExprType, TullyError> {
+        self.loop_depth.set(self.loop_depth.get() + 1);
+        let body = self.statement();
+        self.loop_depth.set(self.loop_depth.get() - 1);
+        body
+    }
+
+    fn var_statement(&self, constant: bool) -> Result<ExprType, TullyError> {
         self.expect_token_or(Identifier, "Expecting variable name")?;
         let t = self.previous();
         if self.match_token(&[Equal]) {
             let value = self.expression()?;
             self.expect_token_or(SemiColon, "Expect ';' after variable declaration")?;
             return Ok(ExprType::Var(Var {
-                name: t.lexeme.clone(),
+                name: t.clone(),
                 initializer: Some(Box::new(value)),
+                constant,
+                slot: Cell::new(None),
             }));
         }
+        if constant {
+            return Err(Parser::error(Some(t), "Expect '=' after const name"));
+        }
         self.expect_token_or(SemiColon, "Expect ';' after variable declaration")?;
         return Ok(ExprType::Var(Var {
-            name: t.lexeme.clone(),
+            name: t.clone(),
             initializer: None,
+            constant,
+            slot: Cell::new(None),
         }));
     }
 
+    fn yield_statement(&self) -> Result<ExprType, TullyError> {
+        let keyword = self.previous().clone();
+        let mut value = None;
+        if !self.check(&SemiColon) {
+            value = Some(Box::new(self.expression()?));
+        }
+        self.expect_token_or(SemiColon, "Expect ';' after yield")?;
+        Ok(ExprType::Yield(Yield { keyword, value }))
+    }
+
     fn print_statement(&self) -> Result<ExprType, TullyError> {
         let expr = self.expression()?;
         self.expect_token_or(SemiColon, "Expect ';' after value ")?;
@@ -127,17 +465,24 @@ impl Parser {
         self.expect_token_or(OpenParen, "Expecting '(' after While")?;
         let condition = Box::new(self.expression()?);
         self.expect_token_or(CloseParen, "Expecting ')' after condition")?;
-        let body = Box::new(self.statement()?);
-        return Ok(ExprType::WhileStatement(WhileStatement { condition, body }));
+        let body = Box::new(self.loop_body()?);
+        return Ok(ExprType::WhileStatement(WhileStatement {
+            condition,
+            body,
+            increment: None,
+        }));
     }
 
     fn for_statement(&self) -> Result<ExprType, TullyError> {
         self.expect_token_or(OpenParen, "Expecting '(' after for")?;
+        if self.check(&Identifier) && self.check_next(&TokenType::In) {
+            return self.for_in_statement();
+        }
         let mut _initializer = None;
         if self.match_token(&[SemiColon]) {
             _initializer = None
         } else if self.match_token(&[TokenType::Var]) {
-            _initializer = Some(self.var_statement()?);
+            _initializer = Some(self.var_statement(false)?);
         } else {
             _initializer = Some(self.expression_statement()?);
         }
@@ -148,20 +493,12 @@ impl Parser {
         self.expect_token_or(SemiColon, "Expecting ';' after condition")?;
         let mut increment = None;
         if !self.check(&CloseParen) {
-            increment = Some(self.expression()?);
+            increment = Some(Box::new(ExprType::ExpressionStmt(Expression {
+                expression: Box::new(self.expression()?),
+            })));
         }
         self.expect_token_or(CloseParen, "Expecting ')' after clauses")?;
-        let mut body = self.statement()?;
-        if let Some(increment) = increment {
-            body = ExprType::Block(Block {
-                statements: vec![
-                    Box::new(body),
-                    Box::new(ExprType::ExpressionStmt(Expression {
-                        expression: Box::new(increment),
-                    })),
-                ],
-            })
-        }
+        let mut body = self.loop_body()?;
         let condition = Box::new(match condition {
             Some(value) => value,
             None => ExprType::Literal(Literal {
@@ -171,6 +508,7 @@ impl Parser {
         body = ExprType::WhileStatement(WhileStatement {
             condition,
             body: Box::new(body),
+            increment,
         });
         if let Some(initializer) = _initializer {
             body = ExprType::Block(Block {
@@ -180,10 +518,102 @@ impl Parser {
         return Ok(body);
     }
 
+    fn for_in_statement(&self) -> Result<ExprType, TullyError> {
+        self.increment();
+        let name = self.previous().clone();
+        self.increment();
+        let keyword = self.previous().clone();
+        let iterable = self.expression()?;
+        self.expect_token_or(CloseParen, "Expecting ')' after iterable")?;
+        let body = self.loop_body()?;
+        Ok(ExprType::ForIn(ForIn {
+            name,
+            keyword,
+            iterable: Box::new(iterable),
+            body: Box::new(body),
+        }))
+    }
+
+    fn class_declaration(&self) -> Result<ExprType, TullyError> {
+        let name = self
+            .expect_token_or(Identifier, "Expect class name")?
+            .clone();
+        let mut superclass = None;
+        if self.match_token(&[Lesser]) {
+            let superclass_name = self
+                .expect_token_or(Identifier, "Expect superclass name")?
+                .clone();
+            if superclass_name.lexeme == name.lexeme {
+                return Err(Parser::error(
+                    Some(&superclass_name),
+                    "A class cannot inherit from itself",
+                ));
+            }
+            superclass = Some(Variable {
+                name: superclass_name,
+                slot: Cell::new(None),
+            });
+        }
+        self.expect_token_or(OpenBrace, "Expect '{' before class body")?;
+        let mut methods = Vec::new();
+        while !self.check(&CloseBrace) && !self.at_end() {
+            // `*name() { ... }` is a generator method
+            let generator = self.match_token(&[Star]);
+            let kind = match self.peek() {
+                Some(t) if t.lexeme == "init" && generator => {
+                    return Err(Parser::error(
+                        Some(t),
+                        "An initializer cannot be a generator",
+                    ))
+                }
+                Some(t) if t.lexeme == "init" => "initializer",
+                _ if generator => "generator method",
+                _ => "method",
+            };
+            if let ExprType::Function(method) = self.function(String::from(kind))? {
+                methods.push(method);
+            }
+        }
+        self.expect_token_or(CloseBrace, "Expect '}' after class body")?;
+        Ok(ExprType::Class(Class {
+            name,
+            superclass,
+            methods,
+            slot: Cell::new(None),
+        }))
+    }
+
     fn function(&self, kind: String) -> Result<ExprType, TullyError> {
         self.expect_token_or(Identifier, &format!("Expect {} name", kind))?;
         let name = self.previous().clone();
         self.expect_token_or(OpenParen, &format!("Expect '(' after {} name", kind))?;
+        Ok(ExprType::Function(Rc::new(
+            self.function_body(name, &kind)?,
+        )))
+    }
+
+    // `fun (a, b) { ... }` and `(a, b) => ...` used as expressions
+    fn lambda(&self, kind: &str) -> Result<ExprType, TullyError> {
+        let mut name = self.previous().clone();
+        name.lexeme = String::from("anonymous");
+        if kind != "arrow function" {
+            self.expect_token_or(OpenParen, "Expect '(' after fun")?;
+        }
+        Ok(ExprType::Lambda(Rc::new(self.function_body(name, kind)?)))
+    }
+
+    // parses the parameters and the body of a function whose '(' has
+    // already been consumed
+    fn function_body(&self, name: Token, kind: &str) -> Result<Function, TullyError> {
+        let enclosing = self.in_initializer.replace(kind == "initializer");
+        let loop_depth = self.loop_depth.replace(0);
+        let function = self.parameters_and_body(name, kind);
+        self.in_initializer.set(enclosing);
+        self.loop_depth.set(loop_depth);
+        function
+    }
+
+    fn parameters_and_body(&self, name: Token, kind: &str) -> Result<Function, TullyError> {
         let mut params = Vec::new();
         if !self.check(&CloseParen) {
             loop {
@@ -193,8 +623,32 @@ impl Parser {
                         "Cannot have more than 255 params",
                     ));
                 }
-                self.expect_token_or(Identifier, "Expect parameter name")?;
-                params.push(self.previous().clone());
+                if let Some(Param { rest: true, .. }) = params.last() {
+                    return Err(Parser::error(
+                        self.peek(),
+                        "Rest parameter must be the last parameter",
+                    ));
+                }
+                let rest = self.match_token(&[Ellipsis]);
+                let name = self
+                    .expect_token_or(Identifier, "Expect parameter name")?
+                    .clone();
+                let default = if !rest && self.match_token(&[Equal]) {
+                    Some(Box::new(self.expression()?))
+                } else {
+                    None
+                };
+                if default.is_none() && !rest && params.iter().any(|p| p.default.is_some()) {
+                    return Err(Parser::error(
+                        Some(&name),
+                        "Parameter without a default after one with a default",
+                    ));
+                }
+                params.push(Param {
+                    name,
+                    default,
+                    rest,
+                });
                 if self.match_token(&[COMMA]) {
                     continue;
                 }
@@ -202,18 +656,63 @@ impl Parser {
             }
         }
         self.expect_token_or(CloseParen, "Expect ')' after parameters")?;
-        self.expect_token_or(OpenBrace, "Expect '{' before {} body")?;
-        let body = Box::new(self.block()?);
-        match *body {
-            ExprType::Block(value) => Ok(ExprType::Function(Function {
+        if kind == "arrow function" {
+            let arrow = self.expect_token_or(Arrow, "Expect '=>' after parameters")?;
+            let keyword = arrow.clone();
+            if !self.match_token(&[OpenBrace]) {
+                let value = self.expression()?;
+                let body = Block {
+                    statements: vec![Box::new(ExprType::Return(Return {
+                        keyword,
+                        value: Some(Box::new(value)),
+                    }))],
+                };
+                return Ok(Function {
+                    name,
+                    params,
+                    body,
+                    generator: false,
+                    slot: Cell::new(None),
+                });
+            }
+        } else {
+            self.expect_token_or(OpenBrace, &format!("Expect '{{' before {} body", kind))?;
+        }
+        match self.block()? {
+            ExprType::Block(body) => Ok(Function {
                 name,
                 params,
-                body: value,
-            })),
+                body,
+                generator: kind.starts_with("generator"),
+                slot: Cell::new(None),
+            }),
             _ => Err(Parser::error(self.peek(), "Expecting block")),
         }
     }
 
+    // `(` starts an arrow function when its matching `)` is followed by `=>`
+    fn is_arrow_function(&self) -> bool {
+        let mut depth = 0;
+        let mut n = self.n.get();
+        while let Some(token) = self.tokens.get(n) {
+            match token.tt {
+                OpenParen => depth += 1,
+                CloseParen => {
+                    depth -= 1;
+                    if depth == 0 {
+                        return match self.tokens.get(n + 1) {
+                            Some(next) => matches!(next.tt, Arrow),
+                            None => false,
+                        };
+                    }
+                }
+                _ => (),
+            }
+            n += 1;
+        }
+        false
+    }
+
     fn block(&self) -> Result<ExprType, TullyError> {
         let mut statements = Vec::new();
         while !self.check(&CloseBrace) && !self.at_end() {
@@ -231,12 +730,27 @@ impl Parser {
         }))
     }
 
+    // expressions nested in a match guard can hold arrow functions again
     fn expression(&self) -> Result<ExprType, TullyError> {
-        self.assignment()
+        let in_guard = self.in_guard.replace(false);
+        let expr = self.assignment();
+        self.in_guard.set(in_guard);
+        expr
     }
 
     fn assignment(&self) -> Result<ExprType, TullyError> {
-        let left = self.equality()?;
+        let left = self.conditional()?;
+        if self.match_token(&[
+            PlusEqual,
+            MinusEqual,
+            StarEqual,
+            SlashEqual,
+            PercentageEqual,
+        ]) {
+            let operator = self.previous().clone();
+            let value = self.assignment()?;
+            return self.compound(left, operator, value, false);
+        }
         if self.match_token(&[Equal]) {
             return match &left {
                 ExprType::Variable(var) => {
@@ -245,6 +759,24 @@ impl Parser {
                     Ok(ExprType::Assign(Assign {
                         name,
                         initializer: Box::new(value),
+                        slot: Cell::new(None),
+                    }))
+                }
+                ExprType::Index(index) => {
+                    let value = self.assignment()?;
+                    Ok(ExprType::SetIndex(SetIndex {
+                        object: index.object.clone(),
+                        bracket: index.bracket.clone(),
+                        index: index.index.clone(),
+                        value: Box::new(value),
+                    }))
+                }
+                ExprType::Get(get) => {
+                    let value = self.assignment()?;
+                    Ok(ExprType::Set(Set {
+                        object: get.object.clone(),
+                        name: get.name.clone(),
+                        value: Box::new(value),
                     }))
                 }
                 _ => Err(Parser::error(
@@ -256,6 +788,106 @@ impl Parser {
         return Ok(left);
     }
 
+    fn conditional(&self) -> Result<ExprType, TullyError> {
+        let condition = self.coalesce()?;
+        if self.match_token(&[Question]) {
+            let then_branch = self.expression()?;
+            self.expect_token_or(Colon, "Expect ':' after then branch of '?'")?;
+            let else_branch = self.conditional()?;
+            return Ok(ExprType::Conditional(Conditional {
+                condition: Box::new(condition),
+                then_branch: Box::new(then_branch),
+                else_branch: Box::new(else_branch),
+            }));
+        }
+        return Ok(condition);
+    }
+
+    fn coalesce(&self) -> Result<ExprType, TullyError> {
+        let mut expr = self.or()?;
+        while self.match_token(&[QuestionQuestion]) {
+            let operator = self.previous().clone();
+            let right = self.or()?;
+            expr = ExprType::Logical(Logical {
+                left: Box::new(expr),
+                right: Box::new(right),
+                operator,
+            });
+        }
+        return Ok(expr);
+    }
+
+    fn or(&self) -> Result<ExprType, TullyError> {
+        let mut expr = self.and()?;
+        while self.match_token(&[TokenType::Or]) {
+            let operator = self.previous().clone();
+            let right = self.and()?;
+            expr = ExprType::Logical(Logical {
+                left: Box::new(expr),
+                right: Box::new(right),
+                operator,
+            });
+        }
+        return Ok(expr);
+    }
+
+    fn and(&self) -> Result<ExprType, TullyError> {
+        let mut expr = self.bit_or()?;
+        while self.match_token(&[TokenType::And]) {
+            let operator = self.previous().clone();
+            let right = self.bit_or()?;
+            expr = ExprType::Logical(Logical {
+                left: Box::new(expr),
+                right: Box::new(right),
+                operator,
+            });
+        }
+        return Ok(expr);
+    }
+
+    // the bitwise operators bind looser than comparisons, like in C
+    fn bit_or(&self) -> Result<ExprType, TullyError> {
+        let mut expr = self.bit_xor()?;
+        while self.match_token(&[Pipe]) {
+            let operator = self.previous().clone();
+            let right = self.bit_xor()?;
+            expr = ExprType::Binary(Binary {
+                left: Box::new(expr),
+                right: Box::new(right),
+                operator,
+            });
+        }
+        return Ok(expr);
+    }
+
+    fn bit_xor(&self) -> Result<ExprType, TullyError> {
+        let mut expr = self.bit_and()?;
+        while self.match_token(&[Caret]) {
+            let operator = self.previous().clone();
+            let right = self.bit_and()?;
+            expr = ExprType::Binary(Binary {
+                left: Box::new(expr),
+                right: Box::new(right),
+                operator,
+            });
+        }
+        return Ok(expr);
+    }
+
+    fn bit_and(&self) -> Result<ExprType, TullyError> {
+        let mut expr = self.equality()?;
+        while self.match_token(&[Ampersand]) {
+            let operator = self.previous().clone();
+            let right = self.equality()?;
+            expr = ExprType::Binary(Binary {
+                left: Box::new(expr),
+                right: Box::new(right),
+                operator,
+            });
+        }
+        return Ok(expr);
+    }
+
     fn equality(&self) -> Result<ExprType, TullyError> {
         let mut expr = self.comparator()?;
         while self.match_token(&[EqualEqual, BangEqual]) {
@@ -271,8 +903,42 @@ impl Parser {
     }
 
     fn comparator(&self) -> Result<ExprType, TullyError> {
-        let mut expr = self.addition()?;
+        let mut expr = self.range()?;
         while self.match_token(&[Greater, GreaterEqual, Lesser, LesserEqual]) {
+            let operator = self.previous().clone();
+            let right = self.range()?;
+            expr = ExprType::Binary(Binary {
+                left: Box::new(expr),
+                right: Box::new(right),
+                operator,
+            });
+        }
+        return Ok(expr);
+    }
+
+    fn range(&self) -> Result<ExprType, TullyError> {
+        let start = self.shift()?;
+        if !self.match_token(&[DotDot, DotDotEqual]) {
+            return Ok(start);
+        }
+        let operator = self.previous().clone();
+        let end = self.shift()?;
+        let mut step = None;
+        if self.check_word("step") {
+            self.increment();
+            step = Some(Box::new(self.shift()?));
+        }
+        Ok(ExprType::Range(Range {
+            start: Box::new(start),
+            operator,
+            end: Box::new(end),
+            step,
+        }))
+    }
+
+    fn shift(&self) -> Result<ExprType, TullyError> {
+        let mut expr = self.addition()?;
+        while self.match_token(&[LessLess, GreaterGreater]) {
             let operator = self.previous().clone();
             let right = self.addition()?;
             expr = ExprType::Binary(Binary {
@@ -300,7 +966,7 @@ impl Parser {
 
     fn multiply(&self) -> Result<ExprType, TullyError> {
         let mut expr = self.unary()?;
-        while self.match_token(&[Star, Slash]) {
+        while self.match_token(&[Star, Slash, TildeSlash, Percentage]) {
             let operator = self.previous().clone();
             let right = self.unary()?;
             expr = ExprType::Binary(Binary {
@@ -312,8 +978,54 @@ impl Parser {
         return Ok(expr);
     }
 
+    // `operator` is the token of the compound assignment or of `++` and
+    // `--`, it is turned into the binary operator it applies
+    fn compound(
+        &self,
+        target: ExprType,
+        operator: Token,
+        value: ExprType,
+        postfix: bool,
+    ) -> Result<ExprType, TullyError> {
+        match &target {
+            ExprType::Variable(_) | ExprType::Index(_) => (),
+            ExprType::Get(_) => (),
+            _ => {
+                return Err(Parser::error(
+                    Some(&operator),
+                    &format!("Invalid target for '{}'", operator.lexeme),
+                ))
+            }
+        }
+        let mut operator = operator;
+        operator.tt = match operator.tt {
+            PlusEqual | PlusPlus => Plus,
+            MinusEqual | MinusMinus => Minus,
+            StarEqual => Star,
+            SlashEqual => Slash,
+            _ => Percentage,
+        };
+        Ok(ExprType::Compound(Compound {
+            target: Box::new(target),
+            operator,
+            value: Box::new(value),
+            postfix,
+        }))
+    }
+
+    fn one() -> ExprType {
+        ExprType::Literal(Literal {
+            value: LiteralValue::Int(1),
+        })
+    }
+
     fn unary(&self) -> Result<ExprType, TullyError> {
-        while self.match_token(&[Plus, Minus, Bang]) {
+        if self.match_token(&[PlusPlus, MinusMinus]) {
+            let operator = self.previous().clone();
+            let target = self.unary()?;
+            return self.compound(target, operator, Parser::one(), false);
+        }
+        if self.match_token(&[Plus, Minus, Bang, Tilde]) {
             let operator = self.previous().clone();
             let expression = self.unary()?;
             return Ok(ExprType::Unary(Unary {
@@ -321,11 +1033,28 @@ impl Parser {
                 operator,
             }));
         }
-        self.call()
+        self.exponent()
+    }
+
+    // `**` binds tighter than unary operators on its left and is right
+    // associative, so `-2 ** 2` is `-(2 ** 2)` and `2 ** -1` still parses
+    fn exponent(&self) -> Result<ExprType, TullyError> {
+        let expr = self.call()?;
+        if self.match_token(&[StarStar]) {
+            let operator = self.previous().clone();
+            let right = self.unary()?;
+            return Ok(ExprType::Binary(Binary {
+                left: Box::new(expr),
+                right: Box::new(right),
+                operator,
+            }));
+        }
+        return Ok(expr);
     }
 
     fn call(&self) -> Result<ExprType, TullyError> {
         let mut expr = self.term()?;
+        let mut optional_chain = false;
         loop {
             if self.match_token(&[OpenParen]) {
                 let open_paren = self.previous().clone();
@@ -338,7 +1067,16 @@ impl Parser {
                                 "Can not have more than 255 arguments",
                             ));
                         }
-                        arguments.push(Box::new(self.expression()?));
+                        if self.match_token(&[Ellipsis]) {
+                            let ellipsis = self.previous().clone();
+                            let value = self.expression()?;
+                            arguments.push(Box::new(ExprType::Spread(Spread {
+                                ellipsis,
+                                value: Box::new(value),
+                            })));
+                        } else {
+                            arguments.push(Box::new(self.expression()?));
+                        }
                         if self.match_token(&[COMMA]) {
                             continue;
                         }
@@ -351,30 +1089,63 @@ impl Parser {
                     callee: Box::new(expr),
                     arguments,
                 })
+            } else if self.match_token(&[TokenType::OpenBracket]) {
+                let bracket = self.previous().clone();
+                let index = self.expression()?;
+                self.expect_token_or(TokenType::CloseBracket, "Expecting ']' after index")?;
+                expr = ExprType::Index(Index {
+                    object: Box::new(expr),
+                    bracket,
+                    index: Box::new(index),
+                });
+            } else if self.match_token(&[TokenType::Dot, QuestionDot]) {
+                let optional = matches!(self.previous().tt, QuestionDot);
+                optional_chain |= optional;
+                let name = self
+                    .expect_token_or(Identifier, "Expect property name after '.'")?
+                    .clone();
+                expr = ExprType::Get(Get {
+                    object: Box::new(expr),
+                    name,
+                    optional,
+                });
             } else {
                 break;
             }
         }
+        if optional_chain {
+            expr = ExprType::OptionalChain(OptionalChain {
+                expression: Box::new(expr),
+            });
+        }
+        if self.match_token(&[PlusPlus, MinusMinus]) {
+            let operator = self.previous().clone();
+            return self.compound(expr, operator, Parser::one(), true);
+        }
         return Ok(expr);
     }
 
     fn term(&self) -> Result<ExprType, TullyError> {
         if self.match_token(&[TokenType::Number]) {
             let t = self.previous();
-            let number: f64 = t.lexeme.parse().unwrap();
-            return Ok(ExprType::Literal(Literal {
-                value: LiteralValue::Float(number),
-            }));
+            return match &t.literal {
+                Some(value) => Ok(ExprType::Literal(Literal {
+                    value: value.clone(),
+                })),
+                None => Err(Parser::error(Some(t), "Invalid number literal")),
+            };
         }
 
         if self.match_token(&[TokenType::String]) {
-            let t = self.previous();
-            let string_value = &t.lexeme[1..t.lexeme.len() - 1];
             return Ok(ExprType::Literal(Literal {
-                value: LiteralValue::String(String::from(string_value)),
+                value: Parser::string_literal(self.previous()),
             }));
         }
 
+        if self.match_token(&[TokenType::Interpolation]) {
+            return self.interpolation();
+        }
+
         if self.match_token(&[TokenType::True]) {
             return Ok(ExprType::Literal(Literal {
                 value: LiteralValue::Boolean(true),
@@ -393,9 +1164,56 @@ impl Parser {
             }));
         }
 
+        if self.match_token(&[TokenType::This]) {
+            return Ok(ExprType::This(This {
+                keyword: self.previous().clone(),
+                slot: Cell::new(None),
+            }));
+        }
+
+        if self.match_token(&[TokenType::Super]) {
+            let keyword = self.previous().clone();
+            self.expect_token_or(TokenType::Dot, "Expect '.' after 'super'")?;
+            let method = self
+                .expect_token_or(Identifier, "Expect superclass method name")?
+                .clone();
+            return Ok(ExprType::Super(Super {
+                keyword,
+                method,
+                slot: Cell::new(None),
+            }));
+        }
+
         if self.match_token(&[TokenType::Identifier]) {
             let t = self.previous();
-            return Ok(ExprType::Variable(Variable { name: t.clone() }));
+            return Ok(ExprType::Variable(Variable {
+                name: t.clone(),
+                slot: Cell::new(None),
+            }));
+        }
+
+        if self.match_token(&[TokenType::OpenBracket]) {
+            return self.list();
+        }
+
+        if self.match_token(&[TokenType::OpenBrace]) {
+            return self.map();
+        }
+
+        if self.match_token(&[TokenType::Fun]) {
+            if self.match_token(&[Star]) {
+                return self.lambda("generator lambda");
+            }
+            return self.lambda("lambda");
+        }
+
+        if self.match_token(&[TokenType::Match]) {
+            return self.match_expression();
+        }
+
+        if self.check(&OpenParen) && !self.in_guard.get() && self.is_arrow_function() {
+            self.increment();
+            return self.lambda("arrow function");
         }
 
         if self.match_token(&[TokenType::OpenParen]) {
@@ -409,6 +1227,95 @@ impl Parser {
         Err(Parser::error(self.peek(), "Unexpected token {:?}"))
     }
 
+    fn list(&self) -> Result<ExprType, TullyError> {
+        let bracket = self.previous().clone();
+        let mut elements = Vec::new();
+        while !self.check(&TokenType::CloseBracket) {
+            elements.push(Box::new(self.expression()?));
+            if !self.match_token(&[COMMA]) {
+                break;
+            }
+        }
+        self.expect_token_or(TokenType::CloseBracket, "Expecting ']' after list elements")?;
+        Ok(ExprType::List(List { bracket, elements }))
+    }
+
+    fn map(&self) -> Result<ExprType, TullyError> {
+        let brace = self.previous().clone();
+        let mut entries = Vec::new();
+        while !self.check(&CloseBrace) {
+            let key = self.expression()?;
+            self.expect_token_or(TokenType::Colon, "Expecting ':' after map key")?;
+            let value = self.expression()?;
+            entries.push((Box::new(key), Box::new(value)));
+            if !self.match_token(&[COMMA]) {
+                break;
+            }
+        }
+        self.expect_token_or(CloseBrace, "Expecting '}' after map entries")?;
+        Ok(ExprType::Map(Map { brace, entries }))
+    }
+
+    // "a ${b} c" is lowered into ("a " + b) + " c"; the leading literal is
+    // kept even when empty so that `+` always concatenates strings
+    fn interpolation(&self) -> Result<ExprType, TullyError> {
+        let mut expr = ExprType::Literal(Literal {
+            value: Parser::string_literal(self.previous()),
+        });
+        loop {
+            let mut operator = self.previous().clone();
+            operator.tt = Plus;
+            operator.lexeme = String::from("${");
+            // the string resuming right at the `}` of an empty `${}`
+            let empty = match self.peek() {
+                Some(t) => {
+                    matches!(t.tt, TokenType::String | TokenType::Interpolation)
+                        && t.lexeme.starts_with('}')
+                }
+                None => false,
+            };
+            if empty {
+                return Err(Parser::error(
+                    Some(&operator),
+                    "Expecting expression inside '${}'",
+                ));
+            }
+            let value = self.expression()?;
+            expr = ExprType::Binary(Binary {
+                left: Box::new(expr),
+                right: Box::new(value),
+                operator: operator.clone(),
+            });
+            let done = !self.match_token(&[TokenType::Interpolation]);
+            if done {
+                self.expect_token_or(
+                    TokenType::String,
+                    "Expecting '}' after interpolated expression",
+                )?;
+            }
+            let value = Parser::string_literal(self.previous());
+            if let LiteralValue::String(segment) = &value {
+                if !segment.is_empty() {
+                    expr = ExprType::Binary(Binary {
+                        left: Box::new(expr),
+                        right: Box::new(ExprType::Literal(Literal { value })),
+                        operator,
+                    });
+                }
+            }
+            if done {
+                return Ok(expr);
+            }
+        }
+    }
+
+    fn string_literal(token: &Token) -> LiteralValue {
+        match &token.literal {
+            Some(value) => value.clone(),
+            None => LiteralValue::String(String::new()),
+        }
+    }
+
     fn match_token(&self, token_types: &[TokenType]) -> bool {
         for t in token_types.iter() {
             if self.check(t) {
@@ -429,6 +1336,22 @@ impl Parser {
         }
     }
 
+    // an identifier spelled `word`, for words that are keywords only in
+    // some places
+    fn check_word(&self, word: &str) -> bool {
+        match self.peek() {
+            Some(token) => self.check(&Identifier) && token.lexeme == word,
+            None => false,
+        }
+    }
+
+    fn check_next(&self, token_type: &TokenType) -> bool {
+        match self.tokens.get(self.n.get() + 1) {
+            Some(t1) => std::mem::discriminant(&t1.tt) == std::mem::discriminant(token_type),
+            _ => false,
+        }
+    }
+
     fn at_end(&self) -> bool {
         if let None = self.next_token() {
             return true;
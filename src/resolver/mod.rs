@@ -0,0 +1,3 @@
+pub use resolver::Resolver;
+
+mod resolver;
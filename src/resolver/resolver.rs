@@ -0,0 +1,634 @@
+use std::collections::{HashMap, HashSet};
+use std::rc::Rc;
+
+use crate::error::TullyError;
+use crate::lexer::token::Token;
+use crate::parser::{
+    Assign, Binary, Block, Break, Call, Class, Compound, Conditional, Continue, ExprType,
+    Expression, ForIn, Function, Get, Group, IfStatement, Import, Index, List, Literal, Logical,
+    Map, Match, MatchArm, OptionalChain, Pattern, Print, Range, Return, Set, SetIndex, Slot,
+    Spread, Super, This, Throw, Try, Unary, Var, Variable, Visitor, WhileStatement, Yield,
+};
+
+#[derive(Clone, Copy, PartialEq)]
+enum FunctionKind {
+    None,
+    Function,
+    Generator,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum ClassKind {
+    None,
+    Class,
+    Subclass,
+}
+
+struct Local {
+    index: usize,
+    defined: bool,
+    constant: bool,
+}
+
+// Walks the AST once before it is evaluated and tells every local variable
+// access which scope and which position in that scope it refers to. The
+// scopes pushed here mirror exactly the ones the evaluator creates at
+// runtime: one per block, one per call holding the parameters and the
+// body, and the `super` and `this` scopes wrapping methods.
+pub struct Resolver {
+    scopes: Vec<HashMap<String, Local>>,
+    // globals declared with `const` so far, the evaluator checks the ones
+    // declared later or in an earlier run
+    constants: HashSet<String>,
+    function: FunctionKind,
+    // a generator can only be suspended by statements it knows how to
+    // resume, see `Resume`; a match is an expression and is not one of them
+    can_yield: bool,
+    class: ClassKind,
+}
+
+impl Resolver {
+    pub fn new() -> Resolver {
+        Resolver {
+            scopes: Vec::new(),
+            constants: HashSet::new(),
+            function: FunctionKind::None,
+            can_yield: false,
+            class: ClassKind::None,
+        }
+    }
+
+    pub fn resolve(&mut self, statements: &[ExprType]) -> Result<(), TullyError> {
+        for statement in statements {
+            self.accept(statement)?;
+        }
+        Ok(())
+    }
+
+    fn accept(&mut self, expr: &ExprType) -> Result<(), TullyError> {
+        match expr {
+            ExprType::Binary(bin) => self.visit_binary_operation(bin),
+            ExprType::Literal(lit) => self.visit_literal(lit),
+            ExprType::Unary(unary) => self.visit_unary(unary),
+            ExprType::Group(group) => self.visit_group(group),
+            ExprType::Print(print) => self.visit_print(print),
+            ExprType::ExpressionStmt(expression) => self.visit_expression(expression),
+            ExprType::Variable(variable) => self.visit_variable(variable),
+            ExprType::Var(var) => self.visit_var(var),
+            ExprType::Assign(assign) => self.visit_assign(assign),
+            ExprType::Block(block) => self.visit_block(block),
+            ExprType::IfStatement(if_statement) => self.visit_if_statement(if_statement),
+            ExprType::WhileStatement(while_statement) => {
+                self.visit_while_statement(while_statement)
+            }
+            ExprType::Call(call) => self.visit_call(call),
+            ExprType::Function(function) => self.visit_function(function),
+            ExprType::Return(return_statement) => self.visit_return(return_statement),
+            ExprType::Logical(logical) => self.visit_logical(logical),
+            ExprType::Class(class) => self.visit_class(class),
+            ExprType::Get(get) => self.visit_get(get),
+            ExprType::Set(set) => self.visit_set(set),
+            ExprType::This(this) => self.visit_this(this),
+            ExprType::Super(super_expr) => self.visit_super(super_expr),
+            ExprType::List(list) => self.visit_list(list),
+            ExprType::Index(index) => self.visit_index(index),
+            ExprType::SetIndex(set_index) => self.visit_set_index(set_index),
+            ExprType::Map(map) => self.visit_map(map),
+            ExprType::Break(break_statement) => self.visit_break(break_statement),
+            ExprType::Continue(continue_statement) => self.visit_continue(continue_statement),
+            ExprType::Lambda(lambda) => self.visit_lambda(lambda),
+            ExprType::Throw(throw) => self.visit_throw(throw),
+            ExprType::Try(try_statement) => self.visit_try(try_statement),
+            ExprType::Import(import) => self.visit_import(import),
+            ExprType::Match(match_expr) => self.visit_match(match_expr),
+            ExprType::Compound(compound) => self.visit_compound(compound),
+            ExprType::Conditional(conditional) => self.visit_conditional(conditional),
+            ExprType::Spread(spread) => self.visit_spread(spread),
+            ExprType::Range(range) => self.visit_range(range),
+            ExprType::ForIn(for_in) => self.visit_for_in(for_in),
+            ExprType::Yield(yield_statement) => self.visit_yield(yield_statement),
+            ExprType::OptionalChain(chain) => self.visit_optional_chain(chain),
+        }
+    }
+
+    fn begin_scope(&mut self) {
+        self.scopes.push(HashMap::new());
+    }
+
+    fn end_scope(&mut self) {
+        self.scopes.pop();
+    }
+
+    // Returns the index of `name` in the innermost scope, or `None` when
+    // it is declared as a global. Declaring a name twice in one scope
+    // reuses its slot.
+    fn declare(&mut self, name: &str) -> Option<usize> {
+        let scope = self.scopes.last_mut()?;
+        let index = scope.len();
+        let local = scope.entry(String::from(name)).or_insert(Local {
+            index,
+            defined: false,
+            constant: false,
+        });
+        local.defined = false;
+        Some(local.index)
+    }
+
+    fn define(&mut self, name: &str) {
+        if let Some(local) = self.scopes.last_mut().and_then(|scope| scope.get_mut(name)) {
+            local.defined = true;
+        }
+    }
+
+    fn declare_and_define(&mut self, name: &str) -> Option<usize> {
+        let index = self.declare(name);
+        self.define(name);
+        index
+    }
+
+    fn is_constant(&self, name: &str) -> bool {
+        for scope in self.scopes.iter().rev() {
+            if let Some(local) = scope.get(name) {
+                return local.constant;
+            }
+        }
+        self.constants.contains(name)
+    }
+
+    fn check_assignable(&self, name: &Token) -> Result<(), TullyError> {
+        if self.is_constant(&name.lexeme) {
+            return Err(Resolver::error(
+                name,
+                &format!("Cannot assign to const {}", name.lexeme),
+            ));
+        }
+        Ok(())
+    }
+
+    // a const can not share its scope with another declaration of its
+    // name, be it a variable, a function, a class or an import
+    fn check_redeclaration(&self, token: &Token, constant: bool) -> Result<(), TullyError> {
+        let name = &token.lexeme;
+        let (declared, declared_constant) = match self.scopes.last() {
+            Some(scope) => match scope.get(name) {
+                Some(local) => (true, local.constant),
+                None => (false, false),
+            },
+            None => (self.constants.contains(name), self.constants.contains(name)),
+        };
+        if declared_constant || (declared && constant) {
+            return Err(Resolver::error(
+                token,
+                &format!("Cannot redeclare const {}", name),
+            ));
+        }
+        Ok(())
+    }
+
+    fn resolve_local(&self, name: &str) -> Option<Slot> {
+        for (depth, scope) in self.scopes.iter().rev().enumerate() {
+            if let Some(local) = scope.get(name) {
+                return Some(Slot {
+                    depth,
+                    index: local.index,
+                });
+            }
+        }
+        None
+    }
+
+    fn resolve_function(
+        &mut self,
+        function: &Function,
+        kind: FunctionKind,
+    ) -> Result<(), TullyError> {
+        let enclosing = self.function;
+        let can_yield = self.can_yield;
+        self.function = kind;
+        self.can_yield = kind == FunctionKind::Generator;
+        self.begin_scope();
+        // a default sees the parameters before it
+        let mut result = Ok(());
+        for param in &function.params {
+            if let Some(default) = &param.default {
+                result = result.and_then(|_| self.accept(default));
+            }
+            self.declare_and_define(&param.name.lexeme);
+        }
+        let result = result.and_then(|_| self.resolve_statements(&function.body.statements));
+        self.end_scope();
+        self.function = enclosing;
+        self.can_yield = can_yield;
+        result
+    }
+
+    fn function_kind(function: &Function) -> FunctionKind {
+        if function.generator {
+            return FunctionKind::Generator;
+        }
+        FunctionKind::Function
+    }
+
+    fn resolve_statements(&mut self, statements: &[Box<ExprType>]) -> Result<(), TullyError> {
+        // functions declared in a block can call each other from their
+        // bodies, whatever order they are declared in
+        for statement in statements {
+            if let ExprType::Function(function) = &**statement {
+                self.declare_and_define(&function.name.lexeme);
+            }
+        }
+        for statement in statements {
+            self.accept(statement)?;
+        }
+        Ok(())
+    }
+
+    fn resolve_arm(&mut self, arm: &MatchArm) -> Result<(), TullyError> {
+        for pattern in &arm.patterns {
+            self.resolve_pattern(pattern)?;
+        }
+        if let Some(guard) = &arm.guard {
+            self.accept(guard)?;
+        }
+        self.accept(&arm.body)
+    }
+
+    // alternatives bind the same names, redeclaring them reuses their slots
+    fn resolve_pattern(&mut self, pattern: &Pattern) -> Result<(), TullyError> {
+        match pattern {
+            Pattern::Wildcard => Ok(()),
+            Pattern::Literal(literal) => self.accept(literal),
+            Pattern::Binding(name, slot) => {
+                slot.set(self.declare_and_define(&name.lexeme));
+                Ok(())
+            }
+            Pattern::List(patterns) => {
+                for pattern in patterns {
+                    self.resolve_pattern(pattern)?;
+                }
+                Ok(())
+            }
+            Pattern::Map(entries) => {
+                for (key, pattern) in entries {
+                    self.accept(key)?;
+                    self.resolve_pattern(pattern)?;
+                }
+                Ok(())
+            }
+        }
+    }
+
+    fn error(token: &Token, message: &str) -> TullyError {
+        TullyError::resolver_error_message(token, message)
+    }
+}
+
+impl Visitor<Result<(), TullyError>> for Resolver {
+    fn visit_binary_operation(&mut self, expr: &Binary) -> Result<(), TullyError> {
+        self.accept(&expr.left)?;
+        self.accept(&expr.right)
+    }
+
+    fn visit_literal(&mut self, _expr: &Literal) -> Result<(), TullyError> {
+        Ok(())
+    }
+
+    fn visit_unary(&mut self, expr: &Unary) -> Result<(), TullyError> {
+        self.accept(&expr.expression)
+    }
+
+    fn visit_group(&mut self, expr: &Group) -> Result<(), TullyError> {
+        self.accept(&expr.expression)
+    }
+
+    fn visit_expression(&mut self, expr: &Expression) -> Result<(), TullyError> {
+        self.accept(&expr.expression)
+    }
+
+    fn visit_print(&mut self, expr: &Print) -> Result<(), TullyError> {
+        self.accept(&expr.expression)
+    }
+
+    fn visit_variable(&mut self, expr: &Variable) -> Result<(), TullyError> {
+        let slot = self.resolve_local(&expr.name.lexeme);
+        if let Some(Slot { depth: 0, index }) = slot {
+            let scope = &self.scopes[self.scopes.len() - 1];
+            if scope
+                .values()
+                .any(|local| local.index == index && !local.defined)
+            {
+                return Err(Resolver::error(
+                    &expr.name,
+                    "Cannot read local variable in its own initializer",
+                ));
+            }
+        }
+        expr.slot.set(slot);
+        Ok(())
+    }
+
+    fn visit_var(&mut self, expr: &Var) -> Result<(), TullyError> {
+        self.check_redeclaration(&expr.name, expr.constant)?;
+        let slot = self.declare(&expr.name.lexeme);
+        if let Some(initializer) = &expr.initializer {
+            self.accept(initializer)?;
+        }
+        self.define(&expr.name.lexeme);
+        if expr.constant {
+            match self.scopes.last_mut() {
+                Some(scope) => {
+                    if let Some(local) = scope.get_mut(&expr.name.lexeme) {
+                        local.constant = true;
+                    }
+                }
+                None => {
+                    self.constants.insert(expr.name.lexeme.clone());
+                }
+            }
+        }
+        expr.slot.set(slot);
+        Ok(())
+    }
+
+    fn visit_assign(&mut self, expr: &Assign) -> Result<(), TullyError> {
+        self.check_assignable(&expr.name)?;
+        self.accept(&expr.initializer)?;
+        expr.slot.set(self.resolve_local(&expr.name.lexeme));
+        Ok(())
+    }
+
+    fn visit_block(&mut self, expr: &Block) -> Result<(), TullyError> {
+        self.begin_scope();
+        let result = self.resolve_statements(&expr.statements);
+        self.end_scope();
+        result
+    }
+
+    fn visit_if_statement(&mut self, expr: &IfStatement) -> Result<(), TullyError> {
+        self.accept(&expr.condition)?;
+        self.accept(&expr.then_branch)?;
+        if let Some(else_branch) = &expr.else_branch {
+            self.accept(else_branch)?;
+        }
+        Ok(())
+    }
+
+    fn visit_while_statement(&mut self, expr: &WhileStatement) -> Result<(), TullyError> {
+        self.accept(&expr.condition)?;
+        self.accept(&expr.body)?;
+        if let Some(increment) = &expr.increment {
+            self.accept(increment)?;
+        }
+        Ok(())
+    }
+
+    fn visit_call(&mut self, expr: &Call) -> Result<(), TullyError> {
+        self.accept(&expr.callee)?;
+        for argument in &expr.arguments {
+            self.accept(argument)?;
+        }
+        Ok(())
+    }
+
+    fn visit_function(&mut self, expr: &Rc<Function>) -> Result<(), TullyError> {
+        self.check_redeclaration(&expr.name, false)?;
+        // defined before the body so that the function can call itself
+        expr.slot.set(self.declare_and_define(&expr.name.lexeme));
+        self.resolve_function(expr, Resolver::function_kind(expr))
+    }
+
+    fn visit_return(&mut self, expr: &Return) -> Result<(), TullyError> {
+        if self.function == FunctionKind::None {
+            return Err(Resolver::error(
+                &expr.keyword,
+                "Cannot return from top-level code",
+            ));
+        }
+        if let Some(value) = &expr.value {
+            if self.function == FunctionKind::Generator {
+                return Err(Resolver::error(
+                    &expr.keyword,
+                    "Cannot return a value from a generator",
+                ));
+            }
+            self.accept(value)?;
+        }
+        Ok(())
+    }
+
+    fn visit_logical(&mut self, expr: &Logical) -> Result<(), TullyError> {
+        self.accept(&expr.left)?;
+        self.accept(&expr.right)
+    }
+
+    fn visit_class(&mut self, expr: &Class) -> Result<(), TullyError> {
+        self.check_redeclaration(&expr.name, false)?;
+        let enclosing = self.class;
+        self.class = ClassKind::Class;
+        expr.slot.set(self.declare_and_define(&expr.name.lexeme));
+        if let Some(superclass) = &expr.superclass {
+            self.class = ClassKind::Subclass;
+            self.visit_variable(superclass)?;
+            self.begin_scope();
+            self.declare_and_define("super");
+        }
+        self.begin_scope();
+        self.declare_and_define("this");
+        let mut result = Ok(());
+        for method in &expr.methods {
+            result = self.resolve_function(method, Resolver::function_kind(method));
+            if result.is_err() {
+                break;
+            }
+        }
+        self.end_scope();
+        if expr.superclass.is_some() {
+            self.end_scope();
+        }
+        self.class = enclosing;
+        result
+    }
+
+    fn visit_get(&mut self, expr: &Get) -> Result<(), TullyError> {
+        self.accept(&expr.object)
+    }
+
+    fn visit_set(&mut self, expr: &Set) -> Result<(), TullyError> {
+        self.accept(&expr.value)?;
+        self.accept(&expr.object)
+    }
+
+    fn visit_this(&mut self, expr: &This) -> Result<(), TullyError> {
+        if self.class == ClassKind::None {
+            return Err(Resolver::error(
+                &expr.keyword,
+                "Cannot use 'this' outside of a class",
+            ));
+        }
+        expr.slot.set(self.resolve_local("this"));
+        Ok(())
+    }
+
+    fn visit_super(&mut self, expr: &Super) -> Result<(), TullyError> {
+        match self.class {
+            ClassKind::None => Err(Resolver::error(
+                &expr.keyword,
+                "Cannot use 'super' outside of a class",
+            )),
+            ClassKind::Class => Err(Resolver::error(
+                &expr.keyword,
+                "Cannot use 'super' in a class with no superclass",
+            )),
+            ClassKind::Subclass => {
+                expr.slot.set(self.resolve_local("super"));
+                Ok(())
+            }
+        }
+    }
+
+    fn visit_list(&mut self, expr: &List) -> Result<(), TullyError> {
+        for element in &expr.elements {
+            self.accept(element)?;
+        }
+        Ok(())
+    }
+
+    fn visit_index(&mut self, expr: &Index) -> Result<(), TullyError> {
+        self.accept(&expr.object)?;
+        self.accept(&expr.index)
+    }
+
+    fn visit_set_index(&mut self, expr: &SetIndex) -> Result<(), TullyError> {
+        self.accept(&expr.object)?;
+        self.accept(&expr.index)?;
+        self.accept(&expr.value)
+    }
+
+    fn visit_map(&mut self, expr: &Map) -> Result<(), TullyError> {
+        for (key, value) in &expr.entries {
+            self.accept(key)?;
+            self.accept(value)?;
+        }
+        Ok(())
+    }
+
+    fn visit_break(&mut self, _expr: &Break) -> Result<(), TullyError> {
+        Ok(())
+    }
+
+    fn visit_continue(&mut self, _expr: &Continue) -> Result<(), TullyError> {
+        Ok(())
+    }
+
+    fn visit_lambda(&mut self, expr: &Rc<Function>) -> Result<(), TullyError> {
+        self.resolve_function(expr, Resolver::function_kind(expr))
+    }
+
+    fn visit_throw(&mut self, expr: &Throw) -> Result<(), TullyError> {
+        self.accept(&expr.value)
+    }
+
+    fn visit_try(&mut self, expr: &Try) -> Result<(), TullyError> {
+        self.visit_block(&expr.body)?;
+        if let Some(catch) = &expr.catch {
+            // the caught error shares a scope with the statements of the
+            // catch block, like parameters do with a function body
+            self.begin_scope();
+            if let Some(name) = &catch.name {
+                catch.slot.set(self.declare_and_define(&name.lexeme));
+            }
+            let result = self.resolve_statements(&catch.body.statements);
+            self.end_scope();
+            result?;
+        }
+        if let Some(finally) = &expr.finally {
+            self.visit_block(finally)?;
+        }
+        Ok(())
+    }
+
+    fn visit_import(&mut self, expr: &Import) -> Result<(), TullyError> {
+        let names = expr.alias.iter().chain(expr.names.iter());
+        for (name, slot) in names.zip(expr.slots.iter()) {
+            self.check_redeclaration(name, false)?;
+            slot.set(self.declare_and_define(&name.lexeme));
+        }
+        Ok(())
+    }
+
+    fn visit_match(&mut self, expr: &Match) -> Result<(), TullyError> {
+        self.accept(&expr.subject)?;
+        let can_yield = std::mem::replace(&mut self.can_yield, false);
+        let mut result = Ok(());
+        for arm in &expr.arms {
+            // every arm gets a scope for the names its patterns bind
+            self.begin_scope();
+            result = self.resolve_arm(arm);
+            self.end_scope();
+            if result.is_err() {
+                break;
+            }
+        }
+        self.can_yield = can_yield;
+        result
+    }
+
+    fn visit_compound(&mut self, expr: &Compound) -> Result<(), TullyError> {
+        if let ExprType::Variable(variable) = &*expr.target {
+            self.check_assignable(&variable.name)?;
+        }
+        self.accept(&expr.target)?;
+        self.accept(&expr.value)
+    }
+
+    fn visit_conditional(&mut self, expr: &Conditional) -> Result<(), TullyError> {
+        self.accept(&expr.condition)?;
+        self.accept(&expr.then_branch)?;
+        self.accept(&expr.else_branch)
+    }
+
+    fn visit_spread(&mut self, expr: &Spread) -> Result<(), TullyError> {
+        self.accept(&expr.value)
+    }
+
+    fn visit_range(&mut self, expr: &Range) -> Result<(), TullyError> {
+        self.accept(&expr.start)?;
+        self.accept(&expr.end)?;
+        if let Some(step) = &expr.step {
+            self.accept(step)?;
+        }
+        Ok(())
+    }
+
+    fn visit_for_in(&mut self, expr: &ForIn) -> Result<(), TullyError> {
+        self.accept(&expr.iterable)?;
+        self.begin_scope();
+        self.declare_and_define(&expr.name.lexeme);
+        let result = self.accept(&expr.body);
+        self.end_scope();
+        result
+    }
+
+    fn visit_yield(&mut self, expr: &Yield) -> Result<(), TullyError> {
+        if self.function != FunctionKind::Generator {
+            return Err(Resolver::error(
+                &expr.keyword,
+                "Cannot yield outside of a generator",
+            ));
+        }
+        if !self.can_yield {
+            return Err(Resolver::error(
+                &expr.keyword,
+                "Cannot yield inside a match",
+            ));
+        }
+        if let Some(value) = &expr.value {
+            self.accept(value)?;
+        }
+        Ok(())
+    }
+
+    fn visit_optional_chain(&mut self, expr: &OptionalChain) -> Result<(), TullyError> {
+        self.accept(&expr.expression)
+    }
+}
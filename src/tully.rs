@@ -10,12 +10,28 @@ impl Tully {
             "nil" => Some(TokenType::Nil),
             "print" => Some(TokenType::Print),
             "var" => Some(TokenType::Var),
+            "const" => Some(TokenType::Const),
             "if" => Some(TokenType::If),
             "else" => Some(TokenType::Else),
             "while" => Some(TokenType::While),
             "for" => Some(TokenType::For),
             "fun" => Some(TokenType::Fun),
             "return" => Some(TokenType::Return),
+            "break" => Some(TokenType::Break),
+            "continue" => Some(TokenType::Continue),
+            "and" => Some(TokenType::And),
+            "or" => Some(TokenType::Or),
+            "class" => Some(TokenType::Class),
+            "this" => Some(TokenType::This),
+            "super" => Some(TokenType::Super),
+            "throw" => Some(TokenType::Throw),
+            "try" => Some(TokenType::Try),
+            "catch" => Some(TokenType::Catch),
+            "finally" => Some(TokenType::Finally),
+            "import" => Some(TokenType::Import),
+            "match" => Some(TokenType::Match),
+            "in" => Some(TokenType::In),
+            "yield" => Some(TokenType::Yield),
             _ => None,
         }
     }
@@ -1,16 +1,38 @@
 use std::cell::RefCell;
+use std::collections::HashMap;
 use std::fmt::{self, Debug, Formatter};
 use std::ops::Deref;
 use std::rc::Rc;
 
-use crate::evaluator::{Callable, TullyCallable};
+#[cfg(feature = "bignum")]
+use bigdecimal::BigDecimal;
+#[cfg(feature = "bignum")]
+use num_bigint::BigInt;
+
+use crate::environment::TullyModule;
+#[cfg(feature = "bignum")]
+use crate::evaluator::bignum;
+use crate::evaluator::{Callable, TullyCallable, TullyClass, TullyInstance, TullyIterator};
 
 #[derive(Clone)]
 pub enum Value {
     Boolean(bool),
+    Int(i64),
     Float(f64),
+    #[cfg(feature = "bignum")]
+    BigInt(BigInt),
+    #[cfg(feature = "bignum")]
+    Decimal(BigDecimal),
     String(String),
     Function(TullyFunction),
+    Class(Rc<TullyClass>),
+    Instance(Rc<RefCell<TullyInstance>>),
+    List(Rc<RefCell<Vec<Rc<Value>>>>),
+    Map(Rc<RefCell<TullyMap>>),
+    Error(Rc<TullyException>),
+    Module(Rc<TullyModule>),
+    Range(TullyRange),
+    Iterator(Rc<RefCell<dyn TullyIterator>>),
     Nil,
 }
 
@@ -27,12 +49,165 @@ impl TullyFunction {
             TullyFunction::NativeFunction(nf) => nf.to_string(),
         }
     }
+
+    fn same(&self, another: &TullyFunction) -> bool {
+        match (self, another) {
+            (TullyFunction::NFunction(f1), TullyFunction::NFunction(f2)) => Rc::ptr_eq(f1, f2),
+            (TullyFunction::NativeFunction(f1), TullyFunction::NativeFunction(f2)) => {
+                Rc::ptr_eq(f1, f2)
+            }
+            _ => false,
+        }
+    }
+}
+
+// `start..end`, `start..=end` and `start..end step n`
+#[derive(Clone, Copy, PartialEq)]
+pub struct TullyRange {
+    pub start: i64,
+    pub end: i64,
+    pub step: i64,
+    pub inclusive: bool,
+}
+
+impl TullyRange {
+    // whether stepping from `start` may reach `value` before the end
+    pub fn contains(&self, value: i64) -> bool {
+        match (self.step > 0, self.inclusive) {
+            (true, true) => value <= self.end,
+            (true, false) => value < self.end,
+            (false, true) => value >= self.end,
+            (false, false) => value > self.end,
+        }
+    }
+}
+
+impl ToString for TullyRange {
+    fn to_string(&self) -> String {
+        let operator = if self.inclusive { "..=" } else { ".." };
+        let range = format!("{}{}{}", self.start, operator, self.end);
+        match self.step {
+            1 => range,
+            step => format!("{} step {}", range, step),
+        }
+    }
+}
+
+// Hashable form of the values that may be used as map keys. Floats holding
+// a whole number share the key of the equal integer and other floats are
+// stored by their bits, so that keys agree with `Value::equals`; NaN is
+// never equal to itself and so cannot be a key. With big numbers enabled
+// floats are keyed by their exact value instead, see `bignum::key`.
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub enum MapKey {
+    Boolean(bool),
+    Int(i64),
+    Float(u64),
+    #[cfg(feature = "bignum")]
+    BigInt(BigInt),
+    #[cfg(feature = "bignum")]
+    Decimal(BigDecimal),
+    String(String),
+}
+
+// Insertion ordered map from `MapKey` to values
+#[derive(Default)]
+pub struct TullyMap {
+    entries: Vec<(Rc<Value>, Rc<Value>)>,
+    indexes: HashMap<MapKey, usize>,
+}
+
+impl TullyMap {
+    pub fn new() -> TullyMap {
+        TullyMap::default()
+    }
+
+    pub fn get(&self, key: &MapKey) -> Option<Rc<Value>> {
+        let index = self.indexes.get(key)?;
+        Some(Rc::clone(&self.entries[*index].1))
+    }
+
+    pub fn contains(&self, key: &MapKey) -> bool {
+        self.indexes.contains_key(key)
+    }
+
+    pub fn insert(&mut self, key: MapKey, key_value: Rc<Value>, value: Rc<Value>) {
+        match self.indexes.get(&key) {
+            Some(index) => self.entries[*index].1 = value,
+            None => {
+                self.indexes.insert(key, self.entries.len());
+                self.entries.push((key_value, value));
+            }
+        }
+    }
+
+    pub fn remove(&mut self, key: &MapKey) -> Option<Rc<Value>> {
+        let index = self.indexes.remove(key)?;
+        let (_, value) = self.entries.remove(index);
+        for position in self.indexes.values_mut() {
+            if *position > index {
+                *position -= 1;
+            }
+        }
+        Some(value)
+    }
+
+    pub fn keys(&self) -> Vec<Rc<Value>> {
+        self.entries.iter().map(|(key, _)| Rc::clone(key)).collect()
+    }
+
+    pub fn values(&self) -> Vec<Rc<Value>> {
+        self.entries
+            .iter()
+            .map(|(_, value)| Rc::clone(value))
+            .collect()
+    }
+}
+
+// A runtime error raised by the evaluator or a native function, caught
+// as a value by `catch`
+#[derive(Debug)]
+pub struct TullyException {
+    pub message: String,
+    pub line: usize,
+    // the token the error was raised at
+    lexeme: String,
+}
+
+impl TullyException {
+    pub fn new(message: String, line: usize, lexeme: String) -> TullyException {
+        TullyException {
+            message,
+            line,
+            lexeme,
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Option<Rc<Value>> {
+        match name {
+            "message" => Some(Rc::new(Value::String(self.message.clone()))),
+            "line" => Some(Rc::new(Value::Int(self.line as i64))),
+            _ => None,
+        }
+    }
+
+    pub fn describe(&self) -> String {
+        format!(
+            "{} at \"{}\" (line {})",
+            self.message, self.lexeme, self.line
+        )
+    }
 }
 
 #[derive(Clone, Debug)]
 pub enum LiteralValue {
     Boolean(bool),
+    Int(i64),
     Float(f64),
+    #[cfg(feature = "bignum")]
+    BigInt(BigInt),
+    #[cfg(feature = "bignum")]
+    Decimal(BigDecimal),
     String(String),
     Nil,
 }
@@ -50,6 +225,18 @@ impl<'a> Constants {
     }
 }
 
+// the integer a float is exactly equal to, if any
+pub fn float_to_int(value: f64) -> Option<i64> {
+    // -2^63 is exact as a float, 2^63 is one past `i64::MAX`
+    if value.fract() == 0.0
+        && value >= -9_223_372_036_854_775_808.0
+        && value < 9_223_372_036_854_775_808.0
+    {
+        return Some(value as i64);
+    }
+    None
+}
+
 impl Debug for Value {
     fn fmt(&self, f: &mut Formatter) -> fmt::Result {
         write!(f, "{}", self.to_string())
@@ -58,6 +245,10 @@ impl Debug for Value {
 
 impl Value {
     pub fn equals(&self, another: &Value) -> bool {
+        #[cfg(feature = "bignum")]
+        if let Some(ordering) = bignum::exact_ordering(self, another) {
+            return ordering == Some(std::cmp::Ordering::Equal);
+        }
         return match self {
             Value::Nil => match another {
                 Value::Nil => true,
@@ -71,26 +262,147 @@ impl Value {
                 Value::String(value2) => value.eq(value2),
                 _ => false,
             },
+            Value::Int(value) => match another {
+                Value::Int(value2) => value == value2,
+                Value::Float(value2) => float_to_int(*value2) == Some(*value),
+                _ => false,
+            },
             Value::Float(value) => match another {
                 Value::Float(value2) => value == value2,
+                Value::Int(value2) => float_to_int(*value) == Some(*value2),
+                _ => false,
+            },
+            Value::Function(value) => match another {
+                Value::Function(value2) => value.same(value2),
+                _ => false,
+            },
+            Value::Class(value) => match another {
+                Value::Class(value2) => Rc::ptr_eq(value, value2),
+                _ => false,
+            },
+            Value::Instance(value) => match another {
+                Value::Instance(value2) => Rc::ptr_eq(value, value2),
+                _ => false,
+            },
+            Value::List(value) => match another {
+                Value::List(value2) => Rc::ptr_eq(value, value2),
                 _ => false,
             },
-            Value::Function(_) => match another {
-                Value::Function(_) => true,
+            Value::Map(value) => match another {
+                Value::Map(value2) => Rc::ptr_eq(value, value2),
                 _ => false,
             },
+            Value::Error(value) => match another {
+                Value::Error(value2) => Rc::ptr_eq(value, value2),
+                _ => false,
+            },
+            Value::Module(value) => match another {
+                Value::Module(value2) => Rc::ptr_eq(value, value2),
+                _ => false,
+            },
+            Value::Range(value) => match another {
+                Value::Range(value2) => value == value2,
+                _ => false,
+            },
+            Value::Iterator(value) => match another {
+                Value::Iterator(value2) => Rc::ptr_eq(value, value2),
+                _ => false,
+            },
+            #[cfg(feature = "bignum")]
+            Value::BigInt(_) | Value::Decimal(_) => false,
         };
     }
+
+    pub fn is_number(&self) -> bool {
+        match self {
+            Value::Int(_) | Value::Float(_) => true,
+            #[cfg(feature = "bignum")]
+            Value::BigInt(_) | Value::Decimal(_) => true,
+            _ => false,
+        }
+    }
+
+    pub fn to_key(&self) -> Option<MapKey> {
+        match self {
+            Value::Boolean(value) => Some(MapKey::Boolean(*value)),
+            Value::String(value) => Some(MapKey::String(value.clone())),
+            Value::Int(value) => Some(MapKey::Int(*value)),
+            Value::Float(value) if value.is_nan() => None,
+            Value::Float(value) => match float_to_int(*value) {
+                Some(value) => Some(MapKey::Int(value)),
+                #[cfg(feature = "bignum")]
+                None if value.is_finite() => bignum::key(self),
+                None => Some(MapKey::Float(value.to_bits())),
+            },
+            #[cfg(feature = "bignum")]
+            Value::BigInt(_) | Value::Decimal(_) => bignum::key(self),
+            _ => None,
+        }
+    }
 }
 
 impl ToString for Value {
     fn to_string(&self) -> String {
+        self.to_string_within(&mut Vec::new())
+    }
+}
+
+impl Value {
+    // `printing` holds the lists and maps this value is inside of, one
+    // that contains itself prints as `[...]` or `{...}` the second time
+    fn to_string_within(&self, printing: &mut Vec<*const ()>) -> String {
         match self {
             Value::Nil => String::from("nil"),
+            Value::Int(value) => format!("{}", value),
             Value::Float(value) => format!("{}", value),
+            #[cfg(feature = "bignum")]
+            Value::BigInt(value) => format!("{}", value),
+            #[cfg(feature = "bignum")]
+            Value::Decimal(value) => format!("{}", value),
             Value::Boolean(value) => format!("{}", value),
             Value::String(value) => value.to_string(),
             Value::Function(func) => func.to_string(),
+            Value::Class(class) => class.to_string(),
+            Value::Instance(instance) => {
+                format!("<{} instance>", instance.deref().borrow().class.name)
+            }
+            Value::List(list) => {
+                let address = Rc::as_ptr(list) as *const ();
+                if printing.contains(&address) {
+                    return String::from("[...]");
+                }
+                printing.push(address);
+                let elements: Vec<String> = list
+                    .deref()
+                    .borrow()
+                    .iter()
+                    .map(|value| value.to_string_within(printing))
+                    .collect();
+                printing.pop();
+                format!("[{}]", elements.join(", "))
+            }
+            Value::Map(map) => {
+                let address = Rc::as_ptr(map) as *const ();
+                if printing.contains(&address) {
+                    return String::from("{...}");
+                }
+                printing.push(address);
+                let entries: Vec<String> = map
+                    .deref()
+                    .borrow()
+                    .entries
+                    .iter()
+                    .map(|(key, value)| {
+                        format!("{}: {}", key.to_string(), value.to_string_within(printing))
+                    })
+                    .collect();
+                printing.pop();
+                format!("{{{}}}", entries.join(", "))
+            }
+            Value::Error(error) => error.message.clone(),
+            Value::Module(module) => format!("<module {}>", module.name()),
+            Value::Range(range) => range.to_string(),
+            Value::Iterator(_) => String::from("<iterator>"),
         }
     }
 }
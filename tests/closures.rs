@@ -0,0 +1,186 @@
+mod common;
+
+use common::{run, try_run};
+
+#[test]
+fn counter_keeps_its_own_state() {
+    let printed = run(r#"
+        fun makeCounter() {
+          var i = 0;
+          fun count() {
+            i = i + 1;
+            return i;
+          }
+          return count;
+        }
+        var a = makeCounter();
+        var b = makeCounter();
+        print a();
+        print a();
+        print b();
+        print a();
+    "#);
+    assert_eq!(printed, ["1", "2", "1", "3"]);
+}
+
+#[test]
+fn counter_stored_in_a_variable_before_returning() {
+    let printed = run(r#"
+        fun makeCounter() {
+          var i = 0;
+          var count = () => {
+            i = i + 1;
+            return i;
+          };
+          var stored = count;
+          return stored;
+        }
+        var counter = makeCounter();
+        counter();
+        print counter();
+    "#);
+    assert_eq!(printed, ["2"]);
+}
+
+#[test]
+fn closures_escaping_through_collections_and_fields() {
+    let printed = run(r#"
+        class Box {}
+        fun fill(box) {
+          var secret = "field";
+          fun reveal() { return secret; }
+          box.reveal = reveal;
+          var list = [() => secret + " list"];
+          return list;
+        }
+        var box = Box();
+        var list = fill(box);
+        print box.reveal();
+        print list[0]();
+    "#);
+    assert_eq!(printed, ["field", "field list"]);
+}
+
+#[test]
+fn callback_sees_the_scope_it_was_declared_in() {
+    let printed = run(r#"
+        fun each(n, callback) {
+          var i = 0;
+          while (i < n) {
+            callback(i);
+            i = i + 1;
+          }
+        }
+        fun sum(n) {
+          var total = 0;
+          var i = 100;
+          fun add(value) {
+            total = total + value + i;
+          }
+          each(n, add);
+          return total;
+        }
+        print sum(4);
+    "#);
+    assert_eq!(printed, ["406"]);
+}
+
+#[test]
+fn nested_closures_share_the_enclosing_variable() {
+    let printed = run(r#"
+        fun first() {
+          var i = 0;
+          fun second() {
+            i = i + 1;
+            var j = i;
+            fun third() {
+              print i + " " + j;
+            }
+            return third;
+          }
+          return second;
+        }
+        var second = first();
+        var a = second();
+        var b = second();
+        a();
+        b();
+    "#);
+    assert_eq!(printed, ["2 1", "2 2"]);
+}
+
+#[test]
+fn parameters_and_blocks_shadow_outer_names() {
+    let printed = run(r#"
+        var x = "global";
+        fun show(x) {
+          return () => x;
+        }
+        var f = show("param");
+        {
+          var x = "block";
+          print f();
+          print x;
+        }
+        print x;
+        fun outer() {
+          var x = "outer";
+          {
+            var x = "inner";
+          }
+          return () => x;
+        }
+        print outer()();
+    "#);
+    assert_eq!(printed, ["param", "block", "global", "outer"]);
+}
+
+#[test]
+fn callee_does_not_see_caller_locals() {
+    let (_, error) = try_run(
+        r#"
+        fun leak() { return hidden; }
+        fun caller() {
+          var hidden = "oops";
+          return leak();
+        }
+        caller();
+    "#,
+    );
+    assert!(error.is_some());
+}
+
+#[test]
+fn closure_ignores_later_shadowing_in_the_same_block() {
+    let printed = run(r#"
+        var a = "global";
+        {
+          fun showA() {
+            print a;
+          }
+          showA();
+          var a = "block";
+          showA();
+        }
+    "#);
+    assert_eq!(printed, ["global", "global"]);
+}
+
+#[test]
+fn local_functions_can_call_later_siblings() {
+    let printed = run(r#"
+        fun outer() {
+          fun f() { return g(); }
+          fun g() { return 1; }
+          return f();
+        }
+        print outer();
+        {
+          fun isEven(n) { if (n == 0) return true; return isOdd(n - 1); }
+          fun isOdd(n) { if (n == 0) return false; return isEven(n - 1); }
+          print isEven(10);
+          print isOdd(7);
+        }
+    "#);
+    assert_eq!(printed, ["1", "true", "true"]);
+}
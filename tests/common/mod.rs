@@ -0,0 +1,53 @@
+// The harness shared by the integration tests: evaluators whose `print`
+// output is captured instead of going to stdout
+#![allow(dead_code)]
+
+use std::cell::RefCell;
+use std::io::{self, Write};
+use std::rc::Rc;
+
+use tully::evaluator::Evaluator;
+
+#[derive(Clone, Default)]
+pub struct Output(Rc<RefCell<Vec<u8>>>);
+
+impl Output {
+    pub fn lines(&self) -> Vec<String> {
+        let printed = String::from_utf8(self.0.borrow().clone()).unwrap();
+        printed.lines().map(String::from).collect()
+    }
+}
+
+impl Write for Output {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.borrow_mut().write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+// an evaluator printing into the returned output
+pub fn evaluator() -> (Evaluator, Output) {
+    let output = Output::default();
+    (Evaluator::with_output(Box::new(output.clone())), output)
+}
+
+// runs `source` and returns the lines it printed, failing the test on an
+// error
+pub fn run(source: &str) -> Vec<String> {
+    let (printed, error) = try_run(source);
+    if let Some(error) = error {
+        panic!("{}", error);
+    }
+    printed
+}
+
+// runs `source` and returns the lines it printed and the error it ended
+// with
+pub fn try_run(source: &str) -> (Vec<String>, Option<String>) {
+    let (mut evaluator, output) = evaluator();
+    let error = evaluator.eval(source).err().map(|e| e.to_string());
+    (output.lines(), error)
+}
@@ -0,0 +1,61 @@
+mod common;
+
+use common::{run, try_run};
+
+#[test]
+fn thrown_values_and_runtime_errors_are_caught() {
+    let printed = run(r#"
+        fun risky(n) {
+          if (n > 1) throw "too big";
+          return n;
+        }
+        try { risky(5); } catch (e) { print "caught " + e; }
+        try { throw {"code": 7}; } catch (e) { print e["code"]; }
+        try {
+          print 1 ~/ 0;
+        } catch (e) {
+          print e.message + " on line " + e.line;
+        }
+    "#);
+    assert_eq!(
+        printed,
+        ["caught too big", "7", "Division by zero on line 8"]
+    );
+}
+
+#[test]
+fn finally_runs_on_return_break_and_throw() {
+    let printed = run(r#"
+        fun f() {
+          try { return "returned"; } finally { print "after return"; }
+        }
+        print f();
+        var i = 0;
+        while (true) {
+          try { i = i + 1; if (i == 2) break; } finally { print "loop " + i; }
+        }
+        try {
+          try { throw "inner"; } finally { print "inner finally"; }
+        } catch (e) {
+          print "outer " + e;
+        }
+    "#);
+    assert_eq!(
+        printed,
+        [
+            "after return",
+            "returned",
+            "loop 1",
+            "loop 2",
+            "inner finally",
+            "outer inner"
+        ]
+    );
+}
+
+#[test]
+fn uncaught_throw_ends_the_script() {
+    let (printed, error) = try_run(r#"print "before"; throw "nope"; print "after";"#);
+    assert_eq!(printed, ["before"]);
+    assert_eq!(error.unwrap(), "Runtime Error : Uncaught nope (line 1)");
+}
@@ -0,0 +1,98 @@
+mod common;
+
+use common::run;
+
+#[test]
+fn resumes_inside_nested_if_while_and_for_in() {
+    let printed = run(r#"
+        fun* nested() {
+          var i = 0;
+          while (i < 3) {
+            var label = "i" + i;
+            if (i % 2 == 0) {
+              for (x in ["a", "b"]) {
+                yield label + x;
+              }
+            } else {
+              yield label;
+            }
+            i = i + 1;
+          }
+        }
+        for (value in nested()) print value;
+    "#);
+    assert_eq!(printed, ["i0a", "i0b", "i1", "i2a", "i2b"]);
+}
+
+#[test]
+fn continue_after_a_yield() {
+    let printed = run(r#"
+        fun* evens() {
+          for (i in 0..4) {
+            yield i;
+            if (i % 2 == 1) continue;
+            yield "even";
+          }
+        }
+        for (value in evens()) print value;
+    "#);
+    assert_eq!(printed, ["0", "even", "1", "2", "even", "3"]);
+}
+
+#[test]
+fn error_thrown_after_a_yield_ends_the_generator() {
+    let printed = run(r#"
+        fun* failing() {
+          yield 1;
+          throw "late";
+        }
+        var it = failing();
+        print it.next();
+        try {
+          it.next();
+        } catch (e) {
+          print "caught " + e;
+        }
+        print it.next();
+    "#);
+    assert_eq!(printed, ["1", "caught late", "nil"]);
+}
+
+#[test]
+fn yields_inside_try_catch_and_finally() {
+    let printed = run(r#"
+        var log = "";
+        fun* resource() {
+          try {
+            yield "body";
+            throw "boom";
+          } catch (e) {
+            log = log + "caught " + e + ", ";
+            yield "catch";
+          } finally {
+            log = log + "released";
+            yield "finally";
+          }
+        }
+        for (value in resource()) print value;
+        print log;
+    "#);
+    assert_eq!(
+        printed,
+        ["body", "catch", "finally", "caught boom, released"]
+    );
+}
+
+#[test]
+fn methods_can_be_generators() {
+    let printed = run(r#"
+        class Bag {
+          init() { this.items = [1, 2]; }
+          *each() {
+            for (item in this.items) yield item * 10;
+          }
+        }
+        for (value in Bag().each()) print value;
+    "#);
+    assert_eq!(printed, ["10", "20"]);
+}
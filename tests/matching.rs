@@ -0,0 +1,63 @@
+mod common;
+
+use common::{run, try_run};
+
+#[test]
+fn literal_binding_list_and_map_patterns() {
+    let printed = run(r#"
+        fun describe(value) {
+          return match (value) {
+            0 => "zero",
+            "x" | "y" => "letter",
+            [first, _] => "pair starting with " + first,
+            {"name": name} => "named " + name,
+            other => "other " + other,
+          };
+        }
+        print describe(0);
+        print describe("y");
+        print describe([1, 2]);
+        print describe({"name": "tully", "age": 3});
+        print describe(7);
+    "#);
+    assert_eq!(
+        printed,
+        [
+            "zero",
+            "letter",
+            "pair starting with 1",
+            "named tully",
+            "other 7"
+        ]
+    );
+}
+
+#[test]
+fn guards_with_and_without_parentheses() {
+    let printed = run(r#"
+        fun size(v) {
+          return match (v) {
+            n if (n > 10) => "big",
+            n if (n > 5) and n < 8 => "mid",
+            n if n < 0 => "negative",
+            _ => "small",
+          };
+        }
+        print size(20);
+        print size(6);
+        print size(-1);
+        print size(9);
+        fun apply(f, x) { return f(x); }
+        print match (3) { n if apply((x) => x > 2, n) => "arrow in guard", _ => "no" };
+    "#);
+    assert_eq!(
+        printed,
+        ["big", "mid", "negative", "small", "arrow in guard"]
+    );
+}
+
+#[test]
+fn no_matching_arm_is_a_runtime_error() {
+    let (_, error) = try_run(r#"print match (3) { 1 => "one", n if n > 5 => "big" };"#);
+    assert!(error.unwrap().contains("No match arm matches 3"));
+}
@@ -0,0 +1,59 @@
+use tully::loader::MemoryLoader;
+
+mod common;
+
+// runs the first of `modules` as the main script, started as `path`, and
+// returns what it printed and the error it ended with
+fn run(modules: &[(&str, &str)], path: &str) -> (Vec<String>, Option<String>) {
+    let mut loader = MemoryLoader::new();
+    for (name, source) in modules {
+        loader.insert(name, source);
+    }
+    let source = modules[0].1;
+    let (mut evaluator, output) = common::evaluator();
+    evaluator.set_loader(Box::new(loader));
+    evaluator.set_path(path);
+    let error = evaluator.eval(source).err().map(|e| e.to_string());
+    (output.lines(), error)
+}
+
+#[test]
+fn modules_run_once_and_resolve_relative_paths() {
+    let (printed, error) = run(
+        &[
+            (
+                "app/main.tl",
+                r#"
+                import "lib/math.tl" as math;
+                from "./lib/math.tl" import square;
+                print math.square(3) + square(2);
+                "#,
+            ),
+            (
+                "app/lib/math.tl",
+                r#"
+                print "loading math";
+                fun square(x) { return x * x; }
+                "#,
+            ),
+        ],
+        "./app/main.tl",
+    );
+    assert_eq!(error, None);
+    assert_eq!(printed, ["loading math", "13"]);
+}
+
+#[test]
+fn importing_the_main_script_back_is_a_cycle() {
+    let (printed, error) = run(
+        &[
+            ("c1.tl", r#"print "c1"; import "c2.tl" as c2;"#),
+            ("c2.tl", r#"print "c2"; import "c1.tl" as c1;"#),
+        ],
+        "c1.tl",
+    );
+    assert_eq!(printed, ["c1", "c2"]);
+    assert!(error
+        .unwrap()
+        .contains("Import cycle: c1.tl -> c2.tl -> c1.tl"));
+}
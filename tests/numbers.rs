@@ -0,0 +1,73 @@
+mod common;
+
+use common::{run, try_run};
+
+#[test]
+fn ints_stay_exact_and_mix_into_floats() {
+    let printed = run(r#"
+        print 9007199254740993;
+        print 7 / 2;
+        print 7 ~/ 2;
+        print -7 ~/ 2;
+        print 1 + 0.5;
+        print int(3.9) + float(2);
+        print 100000000000000000000 > 1;
+    "#);
+    assert_eq!(
+        printed,
+        ["9007199254740993", "3.5", "3", "-4", "1.5", "5", "true"]
+    );
+}
+
+#[test]
+fn int_overflow_is_a_runtime_error() {
+    for source in [
+        "print 9223372036854775807 + 1;",
+        "print -9223372036854775807 - 2;",
+        "print 4611686018427387904 * 2;",
+        "print 2 ** 63;",
+        "var x = -9223372036854775807 - 1; print -x;",
+    ] {
+        let (_, error) = try_run(source);
+        let error = error.unwrap_or_else(|| panic!("no error for {}", source));
+        assert!(error.contains("Integer overflow"), "{}: {}", source, error);
+    }
+}
+
+#[test]
+fn int_division_by_zero_is_a_runtime_error() {
+    let (printed, error) = try_run("print 1.0 / 0; print 1 % 0;");
+    assert_eq!(printed, ["inf"]);
+    assert!(error.unwrap().contains("Division by zero"));
+}
+
+#[test]
+fn radix_separator_and_exponent_literals() {
+    let printed = run(r#"
+        print 0xff;
+        print 0o17 + 0b1010;
+        print 0XFF_FF;
+        print 1_000_000;
+        print 1.5e-3;
+        print 2E2;
+    "#);
+    assert_eq!(printed, ["255", "25", "65535", "1000000", "0.0015", "200"]);
+}
+
+#[test]
+fn malformed_number_literals_are_lexical_errors() {
+    for (source, message) in [
+        ("print 1__0;", "'_' must be between two digits"),
+        ("print 1_;", "'_' must be between two digits"),
+        ("print 0x;", "Expected digits after 0x"),
+        ("print 1e;", "Expected digits after 1e"),
+        ("print 0b102;", "Unexpected character 2 in number literal"),
+        ("print 0xffffffffffffffffff;", "is too large"),
+        ("print 1e400;", "is out of range"),
+    ] {
+        let (_, error) = try_run(source);
+        let error = error.unwrap_or_else(|| panic!("no error for {}", source));
+        assert!(error.starts_with("Lexical Error"), "{}: {}", source, error);
+        assert!(error.contains(message), "{}: {}", source, error);
+    }
+}
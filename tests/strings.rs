@@ -0,0 +1,26 @@
+mod common;
+
+use common::{run, try_run};
+
+#[test]
+fn interpolation_concatenates_like_plus() {
+    let printed = run(r#"
+        var user = "tully";
+        print "Hello ${user}, 1 + 2 = ${1 + 2}!";
+        print "${"nested ${user}"}";
+        print "${1}${2}";
+    "#);
+    assert_eq!(printed, ["Hello tully, 1 + 2 = 3!", "nested tully", "12"]);
+}
+
+#[test]
+fn interpolation_rejects_what_plus_rejects() {
+    let (printed, error) = try_run(r#"print "a" + 1; print "${nil}";"#);
+    assert_eq!(printed, ["a1"]);
+    let error = error.unwrap();
+    assert!(error.contains("Interpolated values"), "{}", error);
+
+    let (_, error) = try_run(r#"print "a${}b";"#);
+    let error = error.unwrap();
+    assert!(error.contains("Expecting expression inside"), "{}", error);
+}